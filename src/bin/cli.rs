@@ -0,0 +1,33 @@
+use clap::{Parser, Subcommand};
+use watchso::{control::ControlRequest, output::OutputFormat};
+
+/// Hot reload Solana programs.
+#[derive(Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+
+    /// Start the control server, overrides `control.enabled` in the config file
+    #[arg(long)]
+    pub control: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Send a request to the running watch process's control server
+    #[cfg(unix)]
+    Ctl {
+        #[command(subcommand)]
+        request: ControlRequest,
+    },
+    /// Save accounts of the running test validator to the fixtures directory
+    Snapshot {
+        /// Account addresses, defaults to `fixtures.accounts` in the config file
+        addresses: Vec<String>,
+    },
+}
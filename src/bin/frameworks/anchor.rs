@@ -1,35 +1,312 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Instant,
 };
 
 use async_trait::async_trait;
+use cargo_toml::Dependency;
+use miette::{Diagnostic, IntoDiagnostic};
+use thiserror::Error;
+use tokio::fs;
 use watchso::{
     command::WCommand,
+    config::Config,
+    constants::{dirname, filename},
     error::WatchError,
     framework::{Framework, WatchableFramework},
-    framework_utils::{get_program_name_path_hashmap, ProjectMap},
+    framework_utils::{
+        get_program_name_path_hashmap, get_pubkey_from_keypair_path, ProgramName, ProjectMap,
+    },
+    glob::glob,
+    hook::{HookEvent, Hooks},
+    idl,
+    output::{print_diagnostic, print_info},
+    toml::{read_anchor_toml, read_cargo_toml},
 };
 
 #[derive(Default)]
 pub struct Anchor {
     /// Starting directory path
     origin: Arc<PathBuf>,
+    /// Project configuration
+    config: Arc<Config>,
     /// Map of program names and paths
     project_map: ProjectMap,
+    /// Hooks that run on watch events
+    hooks: Hooks,
 }
 
 impl Anchor {
-    pub fn new<P: AsRef<Path>>(origin: P) -> Self {
+    pub fn new<P: AsRef<Path>>(origin: P, config: Config) -> Self {
         Self {
             origin: Arc::new(origin.as_ref().to_path_buf()),
+            config: Arc::new(config),
             ..Default::default()
         }
     }
+
+    /// Upgrade the on-chain IDL of the deployed program if it's enabled in the config and the IDL
+    /// has changed since the last upload.
+    async fn upgrade_idl(&self, elf_path: &Path) -> miette::Result<()> {
+        if !self.config.idl.upgrade {
+            return Ok(());
+        }
+        let Some(program_name) = ProgramName::from_elf_path(elf_path) else {
+            return Ok(());
+        };
+        let idl_path = self.idl_path().join(program_name.idl_file_name());
+        let keypair_path = self.deploy_path().join(program_name.keypair_file_name());
+        if !idl_path.exists() || !keypair_path.exists() {
+            return Ok(());
+        }
+
+        let idl = fs::read_to_string(&idl_path).await.into_diagnostic()?;
+        let program_id = get_pubkey_from_keypair_path(keypair_path).await?;
+        if !idl::is_upload_needed(&program_id, &idl).await {
+            return Ok(());
+        }
+
+        // Upgrading fails if the IDL account doesn't exist yet, e.g. after the first deployment
+        for subcommand in ["upgrade", "init"] {
+            let output = WCommand::new(format!(
+                "anchor idl {subcommand} --provider.cluster localnet -f {} {program_id}",
+                idl_path.display()
+            ))
+            .current_dir(self.origin())
+            .output()
+            .await?;
+            if output.status().success() {
+                print_info(format!("Uploaded the IDL of {}", program_name.original()));
+                idl::set_uploaded(program_id, idl).await;
+                return Ok(());
+            }
+        }
+
+        self.warn(format!(
+            "Could not upload the IDL of {}",
+            program_name.original()
+        ))
+        .await
+    }
+
+    /// Run the configured script of `Anchor.toml` or `anchor migrate` once after the given
+    /// deployments.
+    async fn run_deploy_script(&self, elf_paths: &[PathBuf]) -> miette::Result<()> {
+        let Some(script) = &self.config.deploy.anchor_script else {
+            return Ok(());
+        };
+        if elf_paths.is_empty() {
+            return Ok(());
+        }
+        let cmd = match script.as_str() {
+            "migrate" => "anchor migrate --provider.cluster localnet".to_owned(),
+            _ => {
+                let anchor_toml = read_anchor_toml(self.origin()).await?;
+                if !anchor_toml.scripts.contains_key(script) {
+                    return self
+                        .warn(format!(
+                            "Script `{script}` is not in `{}`",
+                            filename::ANCHOR_TOML
+                        ))
+                        .await;
+                }
+                format!("anchor run {script} --provider.cluster localnet")
+            }
+        };
+
+        let programs = elf_paths
+            .iter()
+            .filter_map(ProgramName::from_elf_path)
+            .map(|program_name| program_name.original().to_owned())
+            .collect::<Vec<_>>();
+        let start = Instant::now();
+        let output = WCommand::new(cmd)
+            .current_dir(self.origin())
+            .env("WATCHSO_PROGRAMS", programs.join("\n"))
+            .output()
+            .await?;
+        let programs = programs.join(", ");
+        if output.status().success() {
+            print_info(format!(
+                "Ran `{script}` after deploying {programs} in {:.1}s",
+                start.elapsed().as_secs_f64()
+            ));
+            return Ok(());
+        }
+
+        // Last lines of the output usually include the error
+        let captured = output.captured().unwrap_or_default();
+        let lines = captured.lines().collect::<Vec<_>>();
+        self.warn(format!(
+            "`{script}` failed after deploying {programs}:\n{}",
+            lines[lines.len().saturating_sub(SCRIPT_ERROR_LINES)..].join("\n")
+        ))
+        .await
+    }
+
+    /// Check the installed Anchor and Solana versions, and the `anchor-lang` versions of the
+    /// programs against the `[toolchain]` table of `Anchor.toml`.
+    ///
+    /// The versions are switched first if it's enabled in the config.
+    async fn check_toolchain(&self) -> miette::Result<()> {
+        let Ok(anchor_toml) = read_anchor_toml(self.origin()).await else {
+            return Ok(());
+        };
+        let toolchain = anchor_toml.toolchain;
+
+        if let Some(expected) = &toolchain.anchor_version {
+            if self.config.build.switch_toolchain {
+                self.switch_version("avm use", expected).await?;
+            }
+            let installed = get_installed_version("anchor --version").await;
+            self.check_version("anchor", expected, installed, format!("avm use {expected}"))
+                .await?;
+        }
+
+        if let Some(expected) = &toolchain.solana_version {
+            // `solana-install` was renamed to `agave-install` in Solana 1.18
+            let install = match WCommand::exists("agave-install").await {
+                true => "agave-install init",
+                false => "solana-install init",
+            };
+            if self.config.build.switch_toolchain {
+                self.switch_version(install, expected).await?;
+            }
+            let installed = get_installed_version("solana --version").await;
+            self.check_version(
+                "solana",
+                expected,
+                installed,
+                format!("{install} {expected}"),
+            )
+            .await?;
+        }
+
+        if let Some(expected) = &toolchain.anchor_version {
+            for program_path in self.get_workspace_program_paths().await? {
+                let Ok(manifest) = read_cargo_toml(&program_path).await else {
+                    continue;
+                };
+                let version = match manifest.dependencies.get("anchor-lang") {
+                    Some(Dependency::Simple(version)) => Some(version.to_owned()),
+                    Some(Dependency::Detailed(detail)) => detail.version.to_owned(),
+                    _ => None,
+                };
+                let Some(version) = version else {
+                    continue;
+                };
+                let name = manifest
+                    .package
+                    .map(|package| package.name)
+                    .unwrap_or_else(|| program_path.display().to_string());
+                if !version_matches(&version, expected) {
+                    self.report_mismatch(ToolchainMismatch {
+                        tool: format!("anchor-lang of {name}"),
+                        expected: expected.to_owned(),
+                        installed: version,
+                        help: format!("Set the `anchor-lang` version to {expected}"),
+                    })
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Switch the tool version with the given install command, e.g. `avm use`.
+    ///
+    /// Warns if the command fails or it's not installed.
+    async fn switch_version(&self, install: &str, version: &str) -> miette::Result<()> {
+        let success = WCommand::new(format!("{install} {version}"))
+            .output()
+            .await
+            .is_ok_and(|output| output.status().success());
+        match success {
+            true => {
+                print_info(format!("Switched to {version} with `{install}`"));
+                Ok(())
+            }
+            false => {
+                self.warn(format!("Could not switch to {version} with `{install}`"))
+                    .await
+            }
+        }
+    }
+
+    /// Report a mismatch if the installed version doesn't match the expected version.
+    async fn check_version(
+        &self,
+        tool: &str,
+        expected: &str,
+        installed: Option<String>,
+        help: String,
+    ) -> miette::Result<()> {
+        let Some(installed) = installed else {
+            return Ok(());
+        };
+        if version_matches(&installed, expected) {
+            return Ok(());
+        }
+
+        self.report_mismatch(ToolchainMismatch {
+            tool: tool.to_owned(),
+            expected: expected.to_owned(),
+            installed,
+            help: format!("Switch the version with `{help}`"),
+        })
+        .await
+    }
+
+    /// Print the mismatch and emit [`HookEvent::Warning`].
+    async fn report_mismatch(&self, mismatch: ToolchainMismatch) -> miette::Result<()> {
+        let message = mismatch.to_string();
+        print_diagnostic(mismatch);
+        self.emit(HookEvent::Warning { message }).await
+    }
+
+    /// Get the program paths of the workspace.
+    ///
+    /// `[workspace]` table of `Anchor.toml` takes precedence over the Cargo workspace.
+    async fn get_workspace_program_paths(&self) -> miette::Result<Vec<PathBuf>> {
+        let workspace = read_anchor_toml(self.origin())
+            .await
+            .ok()
+            .and_then(|anchor_toml| anchor_toml.workspace);
+        match workspace {
+            Some(workspace) => {
+                let members = match workspace.members.is_empty() {
+                    true => vec![format!("{}/*", dirname::PROGRAMS)],
+                    false => workspace.members,
+                };
+                glob(self.origin(), members, workspace.exclude, true).await
+            }
+            None => Ok(get_program_name_path_hashmap(self.origin(), |_| true)
+                .await?
+                .into_values()
+                .collect()),
+        }
+    }
 }
 
-/// Default implementation works.
-impl WatchableFramework for Anchor {}
+#[async_trait]
+impl WatchableFramework for Anchor {
+    async fn pathset(&self) -> miette::Result<Vec<PathBuf>> {
+        let mut paths = vec![Path::new(dirname::TARGET).join(dirname::DEPLOY)];
+        paths.extend(self.get_workspace_program_paths().await?);
+
+        Ok(paths)
+    }
+
+    fn discovery_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.origin().join(filename::CARGO_TOML),
+            self.origin().join(filename::ANCHOR_TOML),
+        ]
+    }
+}
 
 #[async_trait]
 impl Framework for Anchor {
@@ -37,25 +314,75 @@ impl Framework for Anchor {
         self.origin.as_path()
     }
 
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
     async fn check_toolset(&self) -> miette::Result<()> {
         const ANCHOR: &str = "anchor";
         if !WCommand::exists(ANCHOR).await {
             Err(WatchError::CommandNotFound(ANCHOR))?
         }
 
-        Ok(())
+        self.check_toolchain().await
+    }
+
+    fn project_map(&self) -> &ProjectMap {
+        &self.project_map
     }
 
     async fn map_program_names(&self) -> miette::Result<()> {
-        for (name, path) in get_program_name_path_hashmap(self.origin()).await? {
-            self.project_map.set_program_path(name, path).await;
+        let anchor_toml = read_anchor_toml(self.origin()).await.ok();
+        let cluster_programs = anchor_toml
+            .as_ref()
+            .and_then(|anchor_toml| anchor_toml.get_cluster_programs());
+
+        // Anchor names the programs, their ELFs and IDLs after the lib name, which may be
+        // different from the package and the directory name. The name is taken from the
+        // `[programs.<cluster>]` table of `Anchor.toml` if it's there, e.g. in kebab-case.
+        let mut program_hm = HashMap::new();
+        for program_path in self.get_workspace_program_paths().await? {
+            let Ok(manifest) = read_cargo_toml(&program_path).await else {
+                continue;
+            };
+            let lib_name = manifest.lib.and_then(|lib| lib.name).or(manifest
+                .package
+                .map(|package| ProgramName::new(package.name).snake_case()));
+            let Some(lib_name) = lib_name else {
+                continue;
+            };
+
+            let name = cluster_programs
+                .and_then(|programs| {
+                    programs
+                        .keys()
+                        .find(|name| ProgramName::new(*name).snake_case() == lib_name)
+                })
+                .cloned()
+                .unwrap_or(lib_name);
+            program_hm.insert(name, program_path);
         }
 
-        Ok(())
-    }
+        if let Some(cluster_programs) = cluster_programs {
+            for name in cluster_programs
+                .keys()
+                .filter(|name| !program_hm.contains_key(*name))
+            {
+                self.warn(format!(
+                    "Program `{name}` of `{}` is not in the workspace",
+                    filename::ANCHOR_TOML
+                ))
+                .await?;
+            }
+        }
+
+        self.project_map.replace(program_hm).await;
 
-    async fn get_program_path(&self, path: &Path) -> Option<PathBuf> {
-        self.project_map.get_program_path(path).await
+        Ok(())
     }
 
     async fn build(&self, program_path: &Path) -> WCommand {
@@ -67,16 +394,89 @@ impl Framework for Anchor {
     }
 
     async fn deploy(&self, elf_path: &Path) -> WCommand {
-        // Anchor still deploys all of the programs in the workspace even after changing the
-        // current dir to the program's dir and it is using program dirname as program name
-        // instead of manifest's package name. Thus, we get the program name from the dirname
-        // and only deploy the modified program.
-        self.get_program_path(elf_path)
-            .await
-            .as_ref()
-            .and_then(|path| path.file_name())
-            .and_then(|name| name.to_str())
-            .map(|name| WCommand::new(format!("anchor deploy -p {name}")))
-            .unwrap_or(WCommand::new("anchor deploy"))
+        // Anchor deploys all of the programs in the workspace by default, the ELF is named after
+        // the lib name that `-p` expects, so only the modified program is deployed
+        let mut command = match ProgramName::from_elf_path(elf_path) {
+            Some(program_name) => {
+                WCommand::new(format!("anchor deploy -p {}", program_name.snake_case()))
+            }
+            None => WCommand::new("anchor deploy"),
+        };
+        command.current_dir(self.origin());
+        command
+    }
+
+    async fn post_deploy(&self, elf_path: &Path) -> miette::Result<()> {
+        self.upgrade_idl(elf_path).await
+    }
+
+    async fn post_deploy_batch(&self, elf_paths: &[PathBuf]) -> miette::Result<()> {
+        self.run_deploy_script(elf_paths).await
+    }
+}
+
+/// Amount of the last output lines that are shown when the deploy script fails.
+const SCRIPT_ERROR_LINES: usize = 10;
+
+/// Version of a tool doesn't match the `[toolchain]` table of `Anchor.toml`.
+#[derive(Error, Diagnostic, Debug)]
+#[error("Expected {tool} {expected} but found {installed}")]
+#[diagnostic(severity(Warning))]
+struct ToolchainMismatch {
+    /// Name of the tool
+    tool: String,
+    /// Version in `Anchor.toml`
+    expected: String,
+    /// Installed or required version
+    installed: String,
+    /// How to fix the mismatch
+    #[help]
+    help: String,
+}
+
+/// Get the installed version of a tool from its version command, e.g. `anchor-cli 0.29.0`.
+async fn get_installed_version(cmd: &str) -> Option<String> {
+    let output = WCommand::new(cmd).output().await.ok()?;
+    output
+        .stdout()
+        .split_whitespace()
+        .nth(1)
+        .map(|version| version.to_owned())
+}
+
+/// Returns whether the version or the version requirement matches the expected version, e.g.
+/// `^0.29` matches `0.29.0`.
+fn version_matches(version: &str, expected: &str) -> bool {
+    let version = version.trim_start_matches(['=', '^', '~', ' ']);
+    version == expected
+        || expected.starts_with(&format!("{version}."))
+        || version.starts_with(&format!("{expected}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_matches_exact_version() {
+        assert!(version_matches("0.29.0", "0.29.0"));
+        assert!(version_matches("=0.29.0", "0.29.0"));
+        assert!(!version_matches("0.29.0", "0.30.1"));
+    }
+
+    #[test]
+    fn version_matches_version_requirement() {
+        assert!(version_matches("^0.29", "0.29.0"));
+        assert!(version_matches("~0.30", "0.30.1"));
+        assert!(version_matches("0", "0.30.1"));
+        assert!(!version_matches("^0.2", "0.29.0"));
+        assert!(!version_matches("0.3", "0.30.1"));
+    }
+
+    #[test]
+    fn version_matches_partial_expected_version() {
+        // `anchor_version = "0.29"` in the `[toolchain]` table of `Anchor.toml`
+        assert!(version_matches("0.29.0", "0.29"));
+        assert!(!version_matches("0.30.0", "0.29"));
     }
 }
@@ -7,6 +7,7 @@ use std::{path::Path, sync::Arc};
 use miette::IntoDiagnostic;
 use tokio::fs;
 use watchso::{
+    config::Config,
     constants::{dirname, filename},
     error::WatchError,
     framework::WatchableFramework,
@@ -20,6 +21,7 @@ use self::{anchor::Anchor, native::Native, seahorse::Seahorse};
 /// program directory.
 pub async fn get_framework_from_path<P: AsRef<Path>>(
     origin: P,
+    config: Config,
 ) -> miette::Result<Arc<dyn WatchableFramework>> {
     let mut item_names = vec![];
 
@@ -34,13 +36,13 @@ pub async fn get_framework_from_path<P: AsRef<Path>>(
         .collect::<Vec<_>>();
 
     if item_names.contains(&dirname::PROGRAMS_PY) {
-        return Ok(Arc::new(Seahorse::new(origin)));
+        return Ok(Arc::new(Seahorse::new(origin, config)));
     }
     if item_names.contains(&filename::ANCHOR_TOML) {
-        return Ok(Arc::new(Anchor::new(origin)));
+        return Ok(Arc::new(Anchor::new(origin, config)));
     }
     if item_names.contains(&filename::CARGO_TOML) {
-        return Ok(Arc::new(Native::new(origin)));
+        return Ok(Arc::new(Native::new(origin, config)));
     }
 
     Err(WatchError::InvalidProgramDirectory(origin.as_ref().into()))?
@@ -4,30 +4,197 @@ use std::{
 };
 
 use async_trait::async_trait;
-use tokio::sync::RwLock;
+use lazy_static::lazy_static;
+use miette::IntoDiagnostic;
+use regex::Regex;
+use tokio::{fs, sync::RwLock};
 use watchso::{
     command::WCommand,
+    config::{Config, ProgramBuildConfig},
+    constants::dirname,
+    error::WatchError,
     framework::{Framework, WatchableFramework},
-    framework_utils::{get_bpf_or_sbf, get_program_name_path_hashmap, ProjectMap},
+    framework_utils::{
+        get_bpf_or_sbf, get_program_name_path_hashmap, get_pubkey_from_keypair_path, is_cdylib,
+        ProgramName, ProjectMap,
+    },
+    hook::Hooks,
+    output::print_info,
+    toml::read_cargo_toml,
 };
 
 #[derive(Default)]
 pub struct Native {
     /// Starting directory path
     origin: Arc<PathBuf>,
+    /// Project configuration
+    config: Arc<Config>,
     /// Map of program names and paths
     project_map: ProjectMap,
+    /// Hooks that run on watch events
+    hooks: Hooks,
     // Full build command to run. Either `cargo build-bpf` or `cargo build-sbf`
     build_cmd: BuildCommand,
 }
 
 impl Native {
-    pub fn new<P: AsRef<Path>>(origin: P) -> Self {
+    pub fn new<P: AsRef<Path>>(origin: P, config: Config) -> Self {
         Self {
             origin: Arc::new(origin.as_ref().to_path_buf()),
+            config: Arc::new(config),
             ..Default::default()
         }
     }
+
+    /// Path of the buffer keypair that is used for deploying the program.
+    ///
+    /// Buffer keypairs are kept outside of `target/deploy` to not be mistaken for program
+    /// keypairs.
+    fn buffer_keypair_path(&self, program_name: &ProgramName) -> PathBuf {
+        self.origin()
+            .join(dirname::TARGET)
+            .join(dirname::BUFFERS)
+            .join(format!("{}-buffer.json", program_name.snake_case()))
+    }
+
+    /// Close the buffer account of the program to reclaim its lamports.
+    ///
+    /// Returns whether the buffer was closed, failures are reported as warnings.
+    async fn close_buffer(&self, program_name: &ProgramName, buffer: &str) -> miette::Result<bool> {
+        lazy_static! {
+            static ref RECLAIMED_REGEX: Regex = Regex::new(r"(\d+(?:\.\d+)?) SOL").unwrap();
+        }
+
+        let output = WCommand::new(format!("solana program close {buffer} --bypass-warning"))
+            .output()
+            .await?;
+        if !output.status().success() {
+            self.warn(format!(
+                "Could not close the buffer {buffer} of {}",
+                program_name.original()
+            ))
+            .await?;
+            return Ok(false);
+        }
+        match RECLAIMED_REGEX.captures(output.stdout()) {
+            Some(captures) => print_info(format!(
+                "Closed the buffer of {} and reclaimed {} SOL",
+                program_name.original(),
+                &captures[1]
+            )),
+            None => print_info(format!("Closed the buffer of {}", program_name.original())),
+        }
+
+        Ok(true)
+    }
+
+    /// Get the name and the build configuration of the program at the given path.
+    async fn get_program_build_config(
+        &self,
+        program_path: &Path,
+    ) -> Option<(String, &ProgramBuildConfig)> {
+        let name = self
+            .project_map
+            .get_all()
+            .await
+            .into_iter()
+            .find(|(_, path)| path == program_path)
+            .map(|(name, _)| name)?;
+        let program_config = self.config.build.programs.get(&name)?;
+
+        Some((name, program_config))
+    }
+
+    /// Copy the ELFs of the programs that are built to a custom output directory to
+    /// `target/deploy`, so that they are deployed. Program keypairs are only copied if they don't
+    /// exist in `target/deploy`.
+    async fn copy_out_dir_artifacts(&self, program_path: &Path) -> miette::Result<()> {
+        for path in self.project_map.get_all().await.into_values() {
+            if !path.starts_with(program_path) {
+                continue;
+            }
+            let Some((name, program_config)) = self.get_program_build_config(&path).await else {
+                continue;
+            };
+            let Some(out_dir) = &program_config.sbf_out_dir else {
+                continue;
+            };
+
+            let out_dir = self.origin().join(out_dir);
+            let deploy_path = self.deploy_path();
+            if out_dir == deploy_path {
+                continue;
+            }
+            fs::create_dir_all(&deploy_path).await.into_diagnostic()?;
+
+            let program_name = ProgramName::new(name);
+            let elf_file_name = program_name.elf_file_name();
+            let keypair_file_name = program_name.keypair_file_name();
+            fs::copy(
+                out_dir.join(&elf_file_name),
+                deploy_path.join(&elf_file_name),
+            )
+            .await
+            .into_diagnostic()?;
+            if !deploy_path.join(&keypair_file_name).exists() {
+                fs::copy(
+                    out_dir.join(&keypair_file_name),
+                    deploy_path.join(&keypair_file_name),
+                )
+                .await
+                .into_diagnostic()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate the IDLs of the programs that depend on `shank` if it's enabled in the config.
+    async fn generate_shank_idls(&self, program_path: &Path) -> miette::Result<()> {
+        if !self.config.idl.shank {
+            return Ok(());
+        }
+
+        for (name, path) in self.project_map.get_all().await {
+            if !path.starts_with(program_path) {
+                continue;
+            }
+            // Only the programs that use the Shank derives have an IDL
+            let uses_shank = read_cargo_toml(&path)
+                .await
+                .is_ok_and(|manifest| manifest.dependencies.contains_key("shank"));
+            if !uses_shank {
+                continue;
+            }
+
+            let program_name = ProgramName::new(&name);
+            let keypair_path = self.deploy_path().join(program_name.keypair_file_name());
+            let program_id = get_pubkey_from_keypair_path(keypair_path).await?;
+            let output = WCommand::new(format!(
+                "shank idl -r {} -o {} -p {program_id}",
+                path.display(),
+                self.idl_path().display()
+            ))
+            .output()
+            .await?;
+            if !output.status().success() {
+                self.warn(format!("Could not generate the IDL of {name}"))
+                    .await?;
+                continue;
+            }
+
+            // Shank names the IDL after the crate name, which may include dashes
+            let shank_idl_path = self.idl_path().join(format!("{name}.json"));
+            let idl_path = self.idl_path().join(program_name.idl_file_name());
+            if shank_idl_path != idl_path && shank_idl_path.exists() {
+                fs::rename(shank_idl_path, idl_path)
+                    .await
+                    .into_diagnostic()?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Default implementation works.
@@ -39,34 +206,217 @@ impl Framework for Native {
         self.origin.as_path()
     }
 
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
     async fn check_toolset(&self) -> miette::Result<()> {
         let build_cmd = get_bpf_or_sbf().await?;
         self.build_cmd.set(build_cmd).await;
 
+        // Buffer keypairs are generated before the deployments
+        const SOLANA_KEYGEN: &str = "solana-keygen";
+        if !WCommand::exists(SOLANA_KEYGEN).await {
+            Err(WatchError::CommandNotFound(SOLANA_KEYGEN))?
+        }
+
+        const SHANK: &str = "shank";
+        if self.config.idl.shank && !WCommand::exists(SHANK).await {
+            Err(WatchError::CommandNotFound(SHANK))?
+        }
+
         Ok(())
     }
 
+    fn project_map(&self) -> &ProjectMap {
+        &self.project_map
+    }
+
     async fn map_program_names(&self) -> miette::Result<()> {
-        for (name, path) in get_program_name_path_hashmap(self.origin()).await? {
-            self.project_map.set_program_path(name, path).await;
-        }
+        // Workspaces may include library crates next to the programs, e.g. the `api` crate of Steel
+        // programs, but only the programs have a `cdylib` target
+        let program_hm = get_program_name_path_hashmap(self.origin(), is_cdylib).await?;
+        self.project_map.replace(program_hm).await;
 
         Ok(())
     }
 
-    async fn get_program_path(&self, path: &Path) -> Option<PathBuf> {
-        self.project_map.get_program_path(path).await
+    async fn setup_build_paths(&self) -> Vec<PathBuf> {
+        // Per-program build options only apply when the programs are built separately
+        if self.config.build.programs.is_empty() {
+            return vec![self.origin().to_path_buf()];
+        }
+
+        let mut program_paths = self
+            .project_map
+            .get_all()
+            .await
+            .into_values()
+            .collect::<Vec<_>>();
+        program_paths.sort();
+        program_paths
     }
 
     async fn build(&self, program_path: &Path) -> WCommand {
-        let mut command = WCommand::new(self.build_cmd.get().await);
+        let build_cmd = self.build_cmd.get().await;
+        let mut command = WCommand::new(build_cmd);
         command.current_dir(program_path);
+
+        let program_config = self
+            .get_program_build_config(program_path)
+            .await
+            .map(|(_, program_config)| program_config);
+        if let Some(program_config) = program_config {
+            if !program_config.features.is_empty() {
+                command
+                    .arg("--features")
+                    .arg(program_config.features.join(","));
+            }
+            if program_config.no_default_features {
+                command.arg("--no-default-features");
+            }
+            if let Some(out_dir) = &program_config.sbf_out_dir {
+                // `cargo build-bpf` only differs by the option prefix
+                let out_dir_arg = match build_cmd.ends_with("bpf") {
+                    true => "--bpf-out-dir",
+                    false => "--sbf-out-dir",
+                };
+                command.arg(out_dir_arg).arg(self.origin().join(out_dir));
+            }
+            if let Some(tools_version) = &program_config.tools_version {
+                command.arg("--tools-version").arg(tools_version);
+            }
+            if let Some(arch) = &program_config.arch {
+                command.arg("--arch").arg(arch);
+            }
+            for (key, value) in &program_config.env {
+                command.env(key, value);
+            }
+        }
+
         command
     }
 
+    async fn post_build(&self, program_path: &Path) -> miette::Result<()> {
+        self.copy_out_dir_artifacts(program_path).await?;
+        self.generate_shank_idls(program_path).await
+    }
+
+    async fn pre_deploy(&self, elf_path: &Path) -> miette::Result<()> {
+        let Some(program_name) = ProgramName::from_elf_path(elf_path) else {
+            Err(WatchError::ProgramNotFound(elf_path.display().to_string()))?
+        };
+        let keypair_path = self.deploy_path().join(program_name.keypair_file_name());
+        if !keypair_path.exists() {
+            Err(WatchError::CouldNotGetKeypair(
+                keypair_path.display().to_string(),
+            ))?
+        }
+
+        let buffer_keypair_path = self.buffer_keypair_path(&program_name);
+        if buffer_keypair_path.exists() {
+            // Buffer that was kept after a failed deployment can't be resumed if the ELF has grown
+            // past its size since then, a new buffer is used instead
+            let buffer = get_pubkey_from_keypair_path(&buffer_keypair_path).await?;
+            let elf_size = fs::metadata(elf_path).await.into_diagnostic()?.len();
+            match get_buffer_data_len(&buffer).await {
+                Some(data_len) if data_len < elf_size => {
+                    print_info(format!(
+                        "Buffer {buffer} of {} is too small for the new ELF",
+                        program_name.original()
+                    ));
+                    if !self.close_buffer(&program_name, &buffer).await? {
+                        // Keep the keypair of the buffer that still exists to close it later
+                        let kept_path =
+                            buffer_keypair_path.with_file_name(format!("{buffer}.json"));
+                        fs::rename(&buffer_keypair_path, &kept_path)
+                            .await
+                            .into_diagnostic()?;
+                        print_info(format!(
+                            "Moved the buffer keypair to `{}`, close it with `solana program \
+                            close {buffer}`",
+                            kept_path.display()
+                        ));
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+        if let Some(buffers_path) = buffer_keypair_path.parent() {
+            fs::create_dir_all(buffers_path).await.into_diagnostic()?;
+        }
+        let output =
+            WCommand::new("solana-keygen new --no-bip39-passphrase --silent --force --outfile")
+                .arg(&buffer_keypair_path)
+                .output()
+                .await?;
+        if !output.status().success() {
+            Err(WatchError::CommandFailed("solana-keygen new"))?
+        }
+
+        Ok(())
+    }
+
     async fn deploy(&self, elf_path: &Path) -> WCommand {
-        WCommand::new(format!("solana program deploy {}", elf_path.display()))
+        // Explicit keypairs make sure the program id doesn't depend on the ELF location, and a
+        // failed deployment can be resumed from the same buffer
+        let mut command = WCommand::new("solana program deploy");
+        command.arg(elf_path);
+        if let Some(program_name) = ProgramName::from_elf_path(elf_path) {
+            command
+                .arg("--program-id")
+                .arg(self.deploy_path().join(program_name.keypair_file_name()))
+                .arg("--buffer")
+                .arg(self.buffer_keypair_path(&program_name));
+        }
+        command
     }
+
+    async fn post_failed_deploy(&self, elf_path: &Path) -> miette::Result<()> {
+        let Some(program_name) = ProgramName::from_elf_path(elf_path) else {
+            return Ok(());
+        };
+        let buffer_keypair_path = self.buffer_keypair_path(&program_name);
+        if !buffer_keypair_path.exists() {
+            return Ok(());
+        }
+        let buffer = get_pubkey_from_keypair_path(&buffer_keypair_path).await?;
+
+        // Deployment may fail before the buffer account is created
+        if get_buffer_data_len(&buffer).await.is_none() {
+            return Ok(());
+        }
+        if !self.config.deploy.close_failed_buffers {
+            print_info(format!(
+                "Kept the buffer {buffer} of {} to resume the next deployment",
+                program_name.original()
+            ));
+            return Ok(());
+        }
+
+        self.close_buffer(&program_name, &buffer).await?;
+
+        Ok(())
+    }
+}
+
+/// Get the data length of the buffer account with `solana program show`.
+///
+/// Returns `None` if the buffer account doesn't exist.
+async fn get_buffer_data_len(buffer: &str) -> Option<u64> {
+    let output = WCommand::new(format!("solana program show {buffer} --output json"))
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status().success())?;
+    serde_json::from_str::<serde_json::Value>(output.stdout())
+        .ok()?
+        .get("dataLen")?
+        .as_u64()
 }
 
 /// Full build command to run. Using `RwLock` because the process is read heavy.
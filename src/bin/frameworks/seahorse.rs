@@ -1,16 +1,21 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use async_trait::async_trait;
 use lazy_static::lazy_static;
+use miette::{Diagnostic, NamedSource, SourceSpan};
 use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+use tokio::fs;
 use watchexec::filter::Filterer;
 use watchso::{
-    action::WAction,
-    command::WCommand,
-    constants::{dirname, extension},
+    action::{PathChange, WAction},
+    command::{CommandOutput, WCommand},
+    config::Config,
+    constants::{dirname, extension, filename},
     error::WatchError,
     framework::{Framework, WatchableFramework},
     framework_utils::{
@@ -18,23 +23,120 @@ use watchso::{
         ProjectMap,
     },
     glob::glob,
+    hook::{HookEvent, Hooks},
+    output::print_diagnostic,
 };
 
 #[derive(Default)]
 pub struct Seahorse {
     /// Starting directory path
     origin: Arc<PathBuf>,
+    /// Project configuration
+    config: Arc<Config>,
     /// Map of program names and paths
     project_map: ProjectMap,
+    /// Hooks that run on watch events
+    hooks: Hooks,
 }
 
 impl Seahorse {
-    pub fn new<P: AsRef<Path>>(origin: P) -> Self {
+    pub fn new<P: AsRef<Path>>(origin: P, config: Config) -> Self {
         Self {
             origin: Arc::new(origin.as_ref().to_path_buf()),
+            config: Arc::new(config),
             ..Default::default()
         }
     }
+
+    /// Get the name of the Python program of the given path, e.g. the ELF path.
+    async fn get_program_name(&self, path: &Path) -> Option<String> {
+        self.get_program_path(path)
+            .await
+            .as_deref()
+            .and_then(get_program_name_from_path)
+            .map(|name| name.to_owned())
+    }
+
+    /// Path of the Anchor program that Seahorse generates from the Python program.
+    fn generated_program_path(&self, program_name: &str) -> PathBuf {
+        self.origin().join(dirname::PROGRAMS).join(program_name)
+    }
+
+    /// Returns whether the modified Python file has become a program or a shared module, i.e.
+    /// whether it has gained or lost its `declare_id`.
+    async fn is_program_status_changed(&self, path: &Path) -> bool {
+        let path = self.origin().join(path);
+        let is_program = fs::read_to_string(&path)
+            .await
+            .is_ok_and(|source| DECLARE_ID_REGEX.is_match(&source));
+        let was_program = self.project_map.contains_program_path(&path).await;
+
+        is_program != was_program
+    }
+
+    /// Get the paths of the programs that are, or that import the given Python module, either
+    /// directly or through other modules.
+    async fn get_dependent_programs(&self, module_path: &Path) -> Vec<PathBuf> {
+        let module_path = self.origin().join(module_path);
+        let mut dependent_programs = vec![];
+        for program_path in self.project_map.get_all().await.into_values() {
+            if self
+                .get_program_modules(&program_path)
+                .await
+                .contains(&module_path)
+            {
+                dependent_programs.push(program_path);
+            }
+        }
+
+        dependent_programs
+    }
+
+    /// Get the paths of the Python modules of the program, i.e. the program itself and the
+    /// modules it imports, either directly or through other modules.
+    async fn get_program_modules(&self, program_path: &Path) -> HashSet<PathBuf> {
+        let programs_py_path = self.origin().join(dirname::PROGRAMS_PY);
+        let mut modules = HashSet::new();
+        let mut stack = vec![program_path.to_path_buf()];
+        while let Some(path) = stack.pop() {
+            if !modules.insert(path.clone()) {
+                continue;
+            }
+            if let Ok(source) = fs::read_to_string(&path).await {
+                stack.extend(parse_imports(&programs_py_path, &path, &source));
+            }
+        }
+
+        modules
+    }
+
+    /// Get the path of the Python module that the generated Rust module is generated from.
+    ///
+    /// `src/dot/program.rs` is generated from the program itself and the other modules in
+    /// `src/dot` from the imported modules with the same path under `programs_py`, e.g.
+    /// `src/dot/util/calc.rs` from `programs_py/util/calc.py`.
+    fn get_python_module_path(
+        &self,
+        program_path: &Path,
+        generated_dir: &Path,
+        generated_path: &Path,
+    ) -> Option<PathBuf> {
+        let dot_path = generated_dir.join(dirname::SRC).join(dirname::DOT);
+        let module_path = generated_path.strip_prefix(dot_path).ok()?;
+        if module_path == Path::new(filename::PROGRAM_RS) {
+            return Some(program_path.to_path_buf());
+        }
+
+        let programs_py_path = self.origin().join(dirname::PROGRAMS_PY);
+        match module_path.file_stem()?.to_str()? {
+            "mod" => Some(
+                programs_py_path
+                    .join(module_path.parent()?)
+                    .join(format!("__init__.{}", extension::PY)),
+            ),
+            _ => Some(programs_py_path.join(module_path.with_extension(extension::PY))),
+        }
+    }
 }
 
 #[async_trait]
@@ -48,6 +150,10 @@ impl WatchableFramework for Seahorse {
         Ok(paths)
     }
 
+    fn discovery_paths(&self) -> Vec<PathBuf> {
+        vec![self.origin().join(dirname::PROGRAMS_PY)]
+    }
+
     async fn filterer(&self) -> Arc<dyn Filterer> {
         let filters = [];
         let ignores = [];
@@ -57,24 +163,40 @@ impl WatchableFramework for Seahorse {
     }
 
     async fn on_action(&self, action: WAction) -> miette::Result<()> {
-        for action_path in action.get_unique_paths() {
+        let mut deployed_elf_paths = vec![];
+        for (action_path, change) in action.get_unique_path_changes() {
             if let Some(ext) = action_path.extension().and_then(|ext| ext.to_str()) {
                 match ext {
                     extension::PY => {
-                        self.build(action_path).await.spawn().await?;
+                        // Python files become programs once they have `declare_id`, e.g. new files
+                        // are usually created empty
+                        let should_refresh = match change {
+                            PathChange::Modify => self.is_program_status_changed(action_path).await,
+                            _ => true,
+                        };
+                        if should_refresh {
+                            self.refresh_programs().await?;
+                        }
+                        if !change.is_removal() {
+                            for program_path in self.get_dependent_programs(action_path).await {
+                                self.run_build(&program_path, CommandOutput::Inherit)
+                                    .await?;
+                            }
+                        }
                     }
                     extension::SO => {
-                        self.deploy(action_path).await.spawn().await?;
-                    }
-                    extension::JSON => {
-                        self.update_program_id(action_path).await?;
+                        let deployed = self.on_elf_change(action_path, change).await?;
+                        if deployed {
+                            deployed_elf_paths.push(action_path.to_path_buf());
+                        }
                     }
+                    extension::JSON => self.on_keypair_change(action_path, change).await?,
                     _ => (),
                 }
             }
         }
 
-        Ok(())
+        self.post_deploy_batch(&deployed_elf_paths).await
     }
 }
 
@@ -84,15 +206,29 @@ impl Framework for Seahorse {
         self.origin.as_path()
     }
 
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
     async fn check_toolset(&self) -> miette::Result<()> {
-        const SEAHORSE: &str = "seahorse";
-        if !WCommand::exists(SEAHORSE).await {
-            Err(WatchError::CommandNotFound(SEAHORSE))?
+        // Seahorse generates Anchor programs, which are built and deployed with Anchor and Solana
+        for cmd in ["seahorse", "anchor", "solana"] {
+            if !WCommand::exists(cmd).await {
+                Err(WatchError::CommandNotFound(cmd))?
+            }
         }
 
         Ok(())
     }
 
+    fn project_map(&self) -> &ProjectMap {
+        &self.project_map
+    }
+
     async fn map_program_names(&self) -> miette::Result<()> {
         let paths = glob(
             self.origin().join(dirname::PROGRAMS_PY),
@@ -102,28 +238,36 @@ impl Framework for Seahorse {
         )
         .await?;
 
+        // Python files without `declare_id` are shared modules rather than programs
+        let mut program_hm = HashMap::new();
         for path in paths {
+            let is_program = fs::read_to_string(&path)
+                .await
+                .is_ok_and(|source| DECLARE_ID_REGEX.is_match(&source));
+            if !is_program {
+                continue;
+            }
             if let Some(program_name) = get_program_name_from_path(&path) {
-                self.project_map
-                    .set_program_path(program_name.to_owned(), path)
-                    .await;
+                program_hm.insert(program_name.to_owned(), path);
             }
         }
+        self.project_map.replace(program_hm).await;
 
         Ok(())
     }
 
-    async fn get_program_path(&self, path: &Path) -> Option<PathBuf> {
-        self.project_map.get_program_path(path).await
-    }
-
-    async fn update_program_id(&self, program_keypair_path: &Path) -> miette::Result<()> {
+    async fn update_program_id(
+        &self,
+        program_keypair_path: &Path,
+    ) -> miette::Result<Option<String>> {
         if let Some(program_path) = self.get_program_path(program_keypair_path).await {
             let program_id = get_pubkey_from_keypair_path(program_keypair_path).await?;
-            update_seahorse_program_id(program_path, program_id).await?;
+            if update_seahorse_program_id(program_path, &program_id).await? {
+                return Ok(Some(program_id));
+            }
         }
 
-        Ok(())
+        Ok(None)
     }
 
     async fn build(&self, program_path: &Path) -> WCommand {
@@ -133,17 +277,248 @@ impl Framework for Seahorse {
         }
     }
 
+    async fn report_build_errors(&self, program_path: &Path, output: &str) -> miette::Result<()> {
+        let Some(program_name) = get_program_name_from_path(program_path) else {
+            return Ok(());
+        };
+        let generated_dir = self.generated_program_path(program_name);
+        let program_modules = self.get_program_modules(program_path).await;
+
+        for rust_error in parse_rust_errors(output) {
+            let generated_path = self.origin().join(&rust_error.path);
+            let Some(python_path) =
+                self.get_python_module_path(program_path, &generated_dir, &generated_path)
+            else {
+                continue;
+            };
+            if !program_modules.contains(&python_path) {
+                continue;
+            }
+            let Ok(python_source) = fs::read_to_string(&python_path).await else {
+                continue;
+            };
+            let Ok(generated_source) = fs::read_to_string(&generated_path).await else {
+                continue;
+            };
+            let Some(span) = map_to_python(&generated_source, rust_error.line, &python_source)
+            else {
+                continue;
+            };
+
+            let python_path = python_path
+                .strip_prefix(self.origin())
+                .unwrap_or(&python_path);
+            let python_line = python_source[..span.offset()].matches('\n').count() + 1;
+            let error = PythonError {
+                message: rust_error.message,
+                source_code: NamedSource::new(python_path.display().to_string(), python_source),
+                span,
+                label: rust_error
+                    .label
+                    .unwrap_or_else(|| "generated from this line".into()),
+                generated: format!("{}:{}", rust_error.path.display(), rust_error.line),
+            };
+            let message = format!("{}:{python_line}: {error}", python_path.display());
+            print_diagnostic(error);
+            self.emit(HookEvent::Error { message }).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn pre_deploy(&self, elf_path: &Path) -> miette::Result<()> {
+        let Some(program_name) = self.get_program_name(elf_path).await else {
+            Err(WatchError::ProgramNotFound(elf_path.display().to_string()))?
+        };
+
+        let generated_path = self.generated_program_path(&program_name);
+        if !generated_path.join(filename::CARGO_TOML).exists() {
+            Err(WatchError::GeneratedProgramNotFound(generated_path))?
+        }
+
+        Ok(())
+    }
+
     async fn deploy(&self, elf_path: &Path) -> WCommand {
-        self.get_program_path(elf_path)
-            .await
-            .as_ref()
-            .and_then(|path| path.file_name())
-            .and_then(|name| name.to_str())
-            .map(|name| WCommand::new(format!("anchor deploy -p {name}")))
-            .unwrap_or(WCommand::new("anchor deploy"))
+        // Seahorse generates an Anchor program with the same name as the Python file, deploy only
+        // that program
+        let mut command = match self.get_program_name(elf_path).await {
+            Some(program_name) => WCommand::new(format!("anchor deploy -p {program_name}")),
+            None => WCommand::new("anchor deploy"),
+        };
+        command.current_dir(self.origin());
+        command
     }
 }
 
+/// Rust compile error of the generated code that is mapped back to the Python source.
+#[derive(Error, Diagnostic, Debug)]
+#[error("{message}")]
+#[diagnostic(help("Generated Rust code: {generated}"))]
+struct PythonError {
+    /// Error message of the Rust compiler
+    message: String,
+    /// Python source of the program
+    #[source_code]
+    source_code: NamedSource,
+    /// Span of the Python line that the error is mapped to
+    #[label("{label}")]
+    span: SourceSpan,
+    /// Primary label of the Rust compiler
+    label: String,
+    /// Location of the error in the generated Rust code
+    generated: String,
+}
+
+/// Compile error of the Rust compiler.
+struct RustError {
+    /// Error message including the error code
+    message: String,
+    /// Path of the file that has the error
+    path: PathBuf,
+    /// Line of the error, starting from 1
+    line: usize,
+    /// Primary label of the error, e.g. ``expected `u8`, found `&str` ``
+    label: Option<String>,
+}
+
+/// Parse the errors of the Rust compiler from the build output, which includes both Seahorse's
+/// and Anchor's output.
+fn parse_rust_errors(output: &str) -> Vec<RustError> {
+    lazy_static! {
+        static ref ERROR_REGEX: Regex = Regex::new(r"^error(?:\[(\w+)\])?: (.+)$").unwrap();
+        static ref LOCATION_REGEX: Regex = Regex::new(r"^\s*--> (.+):(\d+):\d+$").unwrap();
+        static ref LABEL_REGEX: Regex = Regex::new(r"^\s*\|[\s-]*\^+ (.+)$").unwrap();
+    }
+
+    let output = console::strip_ansi_codes(output);
+    let mut errors: Vec<RustError> = vec![];
+    let mut message = None;
+    // Whether the lines belong to the last error
+    let mut in_error = false;
+    for line in output.lines() {
+        if let Some(captures) = ERROR_REGEX.captures(line) {
+            message = Some(match captures.get(1) {
+                Some(code) => format!("{} [{}]", &captures[2], code.as_str()),
+                None => captures[2].to_owned(),
+            });
+        } else if let Some(captures) = LOCATION_REGEX.captures(line) {
+            if let Some(message) = message.take() {
+                errors.push(RustError {
+                    message,
+                    path: PathBuf::from(&captures[1]),
+                    line: captures[2].parse().unwrap_or_default(),
+                    label: None,
+                });
+                in_error = true;
+            }
+        } else if let Some(captures) = LABEL_REGEX.captures(line) {
+            if let Some(error) = errors
+                .last_mut()
+                .filter(|error| in_error && error.label.is_none())
+            {
+                error.label = Some(captures[1].to_owned());
+            }
+        } else if line.trim().is_empty() {
+            in_error = false;
+        }
+    }
+
+    errors
+}
+
+/// Map the line of the generated Rust code to the span of a Python line.
+///
+/// The enclosing function or struct of the Rust line is looked up in the Python source, e.g.
+/// `fn init_handler` is generated from `def init`. The statement inside the definition that shares
+/// the most identifiers with the Rust line is preferred over the definition itself.
+fn map_to_python(generated: &str, line: usize, python: &str) -> Option<SourceSpan> {
+    lazy_static! {
+        static ref DEFINITION_REGEX: Regex =
+            Regex::new(r"^\s*(?:pub(?:\(\w+\))?\s+)?(fn|struct|enum)\s+(\w+)").unwrap();
+        static ref IDENT_REGEX: Regex = Regex::new(r"[A-Za-z_]\w*").unwrap();
+    }
+    /// Identifiers that only exist in the generated Rust code.
+    #[rustfmt::skip]
+    const RUST_IDENTS: &[&str] = &[
+        "let", "mut", "fn", "pub", "self", "Self", "ref", "match", "borrow", "borrow_mut", "clone",
+        "into", "unwrap", "as", "mod", "impl", "Ok", "Err", "Some", "None",
+    ];
+
+    let generated_lines = generated.lines().collect::<Vec<_>>();
+    let error_line = generated_lines.get(line.checked_sub(1)?)?;
+    let python_lines = line_spans(python);
+
+    // Find the closest enclosing definition that exists in the Python source
+    let definition = generated_lines[..line]
+        .iter()
+        .rev()
+        .find_map(|generated_line| {
+            let captures = DEFINITION_REGEX.captures(generated_line)?;
+            let (keyword, name) = match &captures[1] {
+                "fn" => ("def", captures[2].trim_end_matches("_handler")),
+                _ => ("class", &captures[2]),
+            };
+            let regex = Regex::new(&format!(r"^\s*{keyword}\s+{}\b", regex::escape(name))).ok()?;
+            python_lines
+                .iter()
+                .position(|(start, end)| regex.is_match(&python[*start..*end]))
+        })?;
+
+    // Body of the definition is the indented lines after it, the definition itself is a candidate
+    // as well, e.g. for the function parameters
+    let candidates = python_lines[definition..]
+        .iter()
+        .enumerate()
+        .take_while(|(index, (start, end))| {
+            let line = &python[*start..*end];
+            *index == 0 || line.trim().is_empty() || line.starts_with(char::is_whitespace)
+        })
+        .map(|(_, span)| span)
+        .collect::<Vec<_>>();
+
+    let idents = IDENT_REGEX
+        .find_iter(error_line)
+        .map(|m| m.as_str())
+        .filter(|ident| !RUST_IDENTS.contains(ident))
+        .collect::<HashSet<_>>();
+    // Reversed because `max_by_key` returns the last of the equal elements
+    let (start, end) = candidates
+        .into_iter()
+        .rev()
+        .map(|(start, end)| {
+            let score = idents
+                .iter()
+                .filter(|ident| {
+                    IDENT_REGEX
+                        .find_iter(&python[*start..*end])
+                        .any(|m| m.as_str() == **ident)
+                })
+                .count();
+            (score, (*start, *end))
+        })
+        .filter(|(score, _)| *score > 0)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, span)| span)
+        .unwrap_or(python_lines[definition]);
+
+    // Exclude the indentation from the span
+    let indent = python[start..end].len() - python[start..end].trim_start().len();
+    Some((start + indent, end - start - indent).into())
+}
+
+/// Get the byte spans of the lines, excluding the line endings.
+fn line_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut start = 0;
+    text.split_inclusive('\n')
+        .map(|line| {
+            let span = (start, start + line.trim_end_matches(['\r', '\n']).len());
+            start += line.len();
+            span
+        })
+        .collect()
+}
+
 /// Get program name from program's path.
 ///
 /// Seahorse generates Anchor programs based on program's Python file name.
@@ -153,6 +528,77 @@ fn get_program_name_from_path(path: &Path) -> Option<&str> {
         .map(|name| name.trim_end_matches(".py"))
 }
 
+/// Parse the imports of the Python source and resolve them to the module paths.
+///
+/// Absolute imports are resolved from the `programs_py` directory and relative imports from the
+/// directory of the source. Imports that don't resolve to a file, e.g. the standard library, are
+/// ignored.
+fn parse_imports(programs_py_path: &Path, source_path: &Path, source: &str) -> Vec<PathBuf> {
+    lazy_static! {
+        static ref IMPORT_REGEX: Regex = RegexBuilder::new(r"^\s*import\s+(.+)$")
+            .multi_line(true)
+            .build()
+            .unwrap();
+        static ref FROM_IMPORT_REGEX: Regex =
+            RegexBuilder::new(r"^\s*from\s+(\.*)([\w.]*)\s+import\s+\(?([^)\n]+)")
+                .multi_line(true)
+                .build()
+                .unwrap();
+    }
+
+    let resolve = |dots: usize, module: &str| {
+        let base_path = match dots {
+            0 => programs_py_path.to_path_buf(),
+            _ => source_path
+                .ancestors()
+                .nth(dots)
+                .unwrap_or(programs_py_path)
+                .to_path_buf(),
+        };
+        let module_path = module
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .fold(base_path, |path, part| path.join(part));
+        [
+            module_path.with_extension(extension::PY),
+            module_path.join(format!("__init__.{}", extension::PY)),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+    };
+
+    let mut imports = vec![];
+    for captures in IMPORT_REGEX.captures_iter(source) {
+        for module in captures[1].split(',') {
+            // `import a.b as c`
+            let module = module.split_whitespace().next().unwrap_or_default();
+            imports.extend(resolve(0, module));
+        }
+    }
+    for captures in FROM_IMPORT_REGEX.captures_iter(source) {
+        let dots = captures[1].len();
+        let module = &captures[2];
+        imports.extend(resolve(dots, module));
+        // Imported names can be submodules, e.g. `from . import helper`
+        for name in captures[3].split(',') {
+            let name = name.split_whitespace().next().unwrap_or_default();
+            if name != "*" {
+                imports.extend(resolve(dots, &format!("{module}.{name}")));
+            }
+        }
+    }
+
+    imports
+}
+
+lazy_static! {
+    /// `declare_id` call of a Seahorse program.
+    static ref DECLARE_ID_REGEX: Regex = RegexBuilder::new(r#"^declare_id\(("|')(\w*)("|')\)"#)
+        .multi_line(true)
+        .build()
+        .unwrap();
+}
+
 /// Update the file at the given path's `declare_id` function with the given program id.
 ///
 /// Returns whether the program id was updated successfully.
@@ -161,15 +607,196 @@ where
     P: AsRef<Path>,
     S: AsRef<str>,
 {
-    lazy_static! {
-        static ref REGEX: Regex = RegexBuilder::new(r#"^declare_id\(("|')(\w*)("|')\)"#)
-            .multi_line(true)
-            .build()
-            .unwrap();
-    };
-
-    update_file_program_id_with(path, &program_id, |content| {
-        REGEX.captures(content).and_then(|captures| captures.get(2))
+    let updated = update_file_program_id_with(path, &program_id, |content| {
+        DECLARE_ID_REGEX
+            .captures(content)
+            .and_then(|captures| captures.get(2))
     })
-    .await
+    .await?;
+
+    Ok(updated.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATED: &str = r#"use crate::{assign, index_assign, seahorse_util::*};
+use anchor_lang::{prelude::*, solana_program};
+use std::{cell::RefCell, rc::Rc};
+
+#[account]
+#[derive(Debug)]
+pub struct Calculator {
+    pub owner: Pubkey,
+    pub display: i64,
+}
+
+pub fn init_calculator_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut calculator: Empty<Mutable<LoadedCalculator<'info, '_>>>,
+) -> () {
+    let mut calculator = calculator.account.clone();
+
+    assign!(calculator.borrow_mut().owner, owner.key());
+
+    calculator.borrow_mut().display = "0";
+}
+"#;
+
+    const PYTHON: &str = r#"from seahorse.prelude import *
+
+declare_id('Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS')
+
+class Calculator(Account):
+  owner: Pubkey
+  display: i64
+
+@instruction
+def init_calculator(owner: Signer, calculator: Empty[Calculator]):
+  calculator = calculator.init(payer = owner, seeds = ['Calculator', owner])
+  calculator.owner = owner.key()
+  calculator.display = "0"
+"#;
+
+    #[test]
+    fn parse_rust_errors_from_build_output() {
+        let output = "\
+   Compiling calculator v0.1.0 (/home/user/calculator/programs/calculator)
+error[E0308]: mismatched types
+  --> programs/calculator/src/dot/program.rs:20:39
+   |
+20 |     calculator.borrow_mut().display = \"0\";
+   |     -------------------------------   ^^^ expected `i64`, found `&str`
+   |     |
+   |     expected due to the type of this binding
+
+warning: unused variable: `owner`
+  --> programs/calculator/src/lib.rs:35:9
+   |
+35 |         owner: Signer<'info>,
+   |         ^^^^^ help: if this is intentional, prefix it with an underscore: `_owner`
+
+For more information about this error, try `rustc --explain E0308`.
+error: could not compile `calculator` due to previous error; 1 warning emitted
+";
+
+        let errors = parse_rust_errors(output);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "mismatched types [E0308]");
+        assert_eq!(
+            errors[0].path,
+            PathBuf::from("programs/calculator/src/dot/program.rs")
+        );
+        assert_eq!(errors[0].line, 20);
+        assert_eq!(
+            errors[0].label.as_deref(),
+            Some("expected `i64`, found `&str`")
+        );
+    }
+
+    #[test]
+    fn parse_rust_errors_with_label() {
+        let output = "\
+error[E0425]: cannot find value `calculatr` in this scope
+  --> programs/calculator/src/dot/program.rs:18:13
+   |
+18 |     assign!(calculatr.borrow_mut().owner, owner.key());
+   |             ^^^^^^^^^ help: a local variable with a similar name exists: `calculator`
+";
+
+        let errors = parse_rust_errors(output);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "cannot find value `calculatr` in this scope [E0425]"
+        );
+        assert_eq!(errors[0].line, 18);
+        assert_eq!(
+            errors[0].label.as_deref(),
+            Some("help: a local variable with a similar name exists: `calculator`")
+        );
+    }
+
+    #[test]
+    fn map_to_python_statement() {
+        let span = map_to_python(GENERATED, 20, PYTHON).unwrap();
+        let statement = r#"calculator.display = "0""#;
+        assert_eq!(span.offset(), PYTHON.find(statement).unwrap());
+        assert_eq!(span.len(), statement.len());
+    }
+
+    #[test]
+    fn map_to_python_definition() {
+        let span = map_to_python(GENERATED, 7, PYTHON).unwrap();
+        let definition = "class Calculator(Account):";
+        assert_eq!(span.offset(), PYTHON.find(definition).unwrap());
+        assert_eq!(span.len(), definition.len());
+    }
+
+    #[test]
+    fn get_python_module_path_of_generated_modules() {
+        let origin = Path::new("/calculator");
+        let seahorse = Seahorse::new(origin, Config::default());
+        let program_path = origin.join("programs_py/calculator.py");
+        let generated_dir = seahorse.generated_program_path("calculator");
+        let get = |path: &str| {
+            seahorse.get_python_module_path(
+                &program_path,
+                &generated_dir,
+                &generated_dir.join(path),
+            )
+        };
+
+        assert_eq!(get("src/dot/program.rs"), Some(program_path.clone()));
+        assert_eq!(
+            get("src/dot/util/calc.rs"),
+            Some(origin.join("programs_py/util/calc.py"))
+        );
+        assert_eq!(
+            get("src/dot/util/mod.rs"),
+            Some(origin.join("programs_py/util/__init__.py"))
+        );
+        assert_eq!(get("src/lib.rs"), None);
+    }
+
+    #[test]
+    fn parse_imports_resolves_modules() {
+        let programs_py_path =
+            std::env::temp_dir().join(format!("watchso-parse-imports-{}", std::process::id()));
+        let utils_path = programs_py_path.join("utils");
+        std::fs::create_dir_all(&utils_path).unwrap();
+        for path in [
+            utils_path.join("__init__.py"),
+            utils_path.join("math.py"),
+            programs_py_path.join("constants.py"),
+        ] {
+            std::fs::write(path, "").unwrap();
+        }
+
+        let source_path = programs_py_path.join("calculator.py");
+        let source = "\
+from seahorse.prelude import *
+from utils.math import add, sub
+from . import constants
+import utils, os
+";
+        let imports = parse_imports(&programs_py_path, &source_path, source);
+        let nested_imports = parse_imports(
+            &programs_py_path,
+            &utils_path.join("math.py"),
+            "from ..constants import FEE\n",
+        );
+        std::fs::remove_dir_all(&programs_py_path).unwrap();
+
+        assert_eq!(
+            imports,
+            vec![
+                utils_path.join("__init__.py"),
+                utils_path.join("math.py"),
+                programs_py_path.join("constants.py"),
+            ]
+        );
+        assert_eq!(nested_imports, vec![programs_py_path.join("constants.py")]);
+    }
 }
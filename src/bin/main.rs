@@ -1,14 +1,92 @@
+mod cli;
 mod frameworks;
 
-use std::env;
+use std::{
+    env,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use clap::Parser;
+use cli::{Cli, Command};
 use frameworks::get_framework_from_path;
 use miette::IntoDiagnostic;
-use watchso::watch;
+use watchso::{
+    config::Config,
+    control::{self, ControlRequest, ControlResponse},
+    fixtures,
+    hook::Hooks,
+    output::OutputFormat,
+    watch,
+};
 
 #[tokio::main]
 async fn main() -> miette::Result<()> {
+    let cli = Cli::parse();
     let origin = env::current_dir().into_diagnostic()?;
-    let framework = get_framework_from_path(origin).await?;
+    let mut config = Config::read(&origin).await?;
+
+    match cli.command {
+        #[cfg(unix)]
+        Some(Command::Ctl { request }) => {
+            return ctl(config.control.get_socket_path(&origin), request).await
+        }
+        Some(Command::Snapshot { addresses }) => {
+            return fixtures::snapshot(&origin, &config.fixtures, addresses).await
+        }
+        None => (),
+    }
+
+    cli.output.set();
+    if cli.control {
+        config.control.enabled = true;
+    }
+
+    let framework = get_framework_from_path(origin, config).await?;
+    if cli.output == OutputFormat::Json {
+        print_json_events(framework.hooks()).await;
+    }
+
     watch(framework).await
 }
+
+/// Print all events as newline-delimited JSON with a `timestamp_ms` field.
+async fn print_json_events(hooks: &Hooks) {
+    hooks
+        .subscribe_all(|event| {
+            let mut value = serde_json::to_value(event).into_diagnostic()?;
+            if let Some(object) = value.as_object_mut() {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_millis() as u64)
+                    .unwrap_or_default();
+                object.insert("timestamp_ms".into(), timestamp.into());
+            }
+
+            println!("{value}");
+            Ok(())
+        })
+        .await;
+}
+
+/// Send the request to the control server and print the response.
+#[cfg(unix)]
+async fn ctl<P: AsRef<Path>>(socket_path: P, request: ControlRequest) -> miette::Result<()> {
+    match control::send(socket_path, &request).await? {
+        ControlResponse::Ok => Ok(()),
+        ControlResponse::Status(status) => {
+            println!("Paused: {}", status.paused);
+            for program in status.programs {
+                println!(
+                    "{} {} {}",
+                    program.name,
+                    program.program_id.as_deref().unwrap_or("-"),
+                    program.path.display()
+                );
+            }
+
+            Ok(())
+        }
+        ControlResponse::Error { message } => Err(miette::miette!(message)),
+    }
+}
@@ -1,8 +1,18 @@
 //! Utilities for [`Action`].
 
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
-use watchexec::{action::Action, event::Event, signal::source::MainSignal};
+use watchexec::{
+    action::Action,
+    event::{
+        filekind::{FileEventKind, ModifyKind, RenameMode},
+        Event, Tag,
+    },
+    signal::source::MainSignal,
+};
 
 /// Utility struct for [`Action`].
 pub struct WAction(Action);
@@ -37,6 +47,25 @@ impl WAction {
         hashset
     }
 
+    /// Get all the unique paths in the action event paths with the kind of change that has
+    /// happened to them.
+    ///
+    /// If a path has changed multiple times within the same action, the last change is used.
+    pub fn get_unique_path_changes(&self) -> HashMap<&Path, PathChange> {
+        let mut hashmap = HashMap::new();
+        for event in self.0.events.iter() {
+            let kind = event.tags.iter().find_map(|tag| match tag {
+                Tag::FileEventKind(kind) => Some(kind),
+                _ => None,
+            });
+
+            for (i, (path, _)) in event.paths().enumerate() {
+                hashmap.insert(path, PathChange::new(kind, path, i));
+            }
+        }
+        hashmap
+    }
+
     /// Returns whether any signal includes the given signal in the events list.
     fn is_any_signal(&self, signal: MainSignal) -> bool {
         self.0
@@ -46,3 +75,46 @@ impl WAction {
             .any(|sig| sig == signal)
     }
 }
+
+/// Kind of the change that has happened to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathChange {
+    /// Path has been created.
+    Create,
+    /// Path has been modified.
+    Modify,
+    /// Path has been removed.
+    Remove,
+    /// Path has been renamed and this is the old path.
+    RenameFrom,
+    /// Path has been renamed and this is the new path.
+    RenameTo,
+}
+
+impl PathChange {
+    /// Create a new [`PathChange`] from the file event kind.
+    ///
+    /// `index` is the index of the `path` in the event paths, which is used to differentiate the
+    /// old and the new paths of renames that include both paths.
+    fn new(kind: Option<&FileEventKind>, path: &Path, index: usize) -> Self {
+        match kind {
+            Some(FileEventKind::Create(_)) => Self::Create,
+            Some(FileEventKind::Remove(_)) => Self::Remove,
+            Some(FileEventKind::Modify(ModifyKind::Name(mode))) => match mode {
+                RenameMode::From => Self::RenameFrom,
+                RenameMode::To => Self::RenameTo,
+                RenameMode::Both if index == 0 => Self::RenameFrom,
+                RenameMode::Both => Self::RenameTo,
+                // Some platforms don't specify the rename mode
+                _ if path.exists() => Self::RenameTo,
+                _ => Self::RenameFrom,
+            },
+            _ => Self::Modify,
+        }
+    }
+
+    /// Returns whether the path doesn't exist anymore after the change.
+    pub fn is_removal(&self) -> bool {
+        matches!(self, Self::Remove | Self::RenameFrom)
+    }
+}
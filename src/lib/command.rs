@@ -1,13 +1,20 @@
 //! Utilities for commands.
 
 use std::{
+    ffi::OsStr,
     fmt::Display,
     path::Path,
-    process::{ExitStatus, Output},
+    process::{ExitStatus, Output, Stdio},
 };
 
+use console::Term;
 use miette::IntoDiagnostic;
-use tokio::process::Command;
+use tokio::{
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::{Child, Command},
+};
+
+use crate::output::OutputFormat;
 
 /// Utility struct for [`Command`].
 pub struct WCommand(Command);
@@ -22,6 +29,33 @@ impl WCommand {
         Self(cmd)
     }
 
+    /// Create a new [`WCommand`] that runs the given command in a shell.
+    ///
+    /// Unlike [`WCommand::new`], this allows shell features such as pipes and quotes.
+    pub fn shell<C: AsRef<str>>(cmd: C) -> Self {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut command = Command::new(shell);
+        command.arg(flag).arg(cmd.as_ref());
+
+        Self(command)
+    }
+
+    /// Add an argument to the command.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.0.arg(arg);
+        self
+    }
+
+    /// Set an environment variable of the command.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, value: V) -> &mut Self {
+        self.0.env(key, value);
+        self
+    }
+
     /// Set the current directory of the command.
     pub fn current_dir<D: AsRef<Path>>(&mut self, dir: D) -> &mut Self {
         self.0.current_dir(dir);
@@ -50,6 +84,73 @@ impl WCommand {
             .map(|status| status.success())
     }
 
+    /// Spawn the command in the background without waiting for it to exit.
+    ///
+    /// Output of the command is ignored.
+    pub fn spawn_background(&mut self) -> miette::Result<Child> {
+        self.0
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .into_diagnostic()
+    }
+
+    /// Run the command with the given output handling.
+    ///
+    /// Output is always captured if the output format is not [`OutputFormat::Human`]. Inherited
+    /// output results in empty stdout and stderr.
+    ///
+    /// [`CommandOutput::Tee`] output may include ANSI escape codes because the colors are forced.
+    pub async fn run(&mut self, output: CommandOutput) -> miette::Result<ReadableOutput> {
+        match output {
+            CommandOutput::Inherit if OutputFormat::is_human() => {
+                let status = self
+                    .0
+                    .spawn()
+                    .into_diagnostic()?
+                    .wait()
+                    .await
+                    .into_diagnostic()?;
+
+                Ok(Output {
+                    status,
+                    stdout: vec![],
+                    stderr: vec![],
+                }
+                .into())
+            }
+            CommandOutput::Tee if OutputFormat::is_human() => {
+                // Output is piped, force colors to keep the same output as inherited
+                if Term::stdout().features().colors_supported() {
+                    self.0.env("CARGO_TERM_COLOR", "always");
+                }
+                let mut child = self
+                    .0
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .into_diagnostic()?;
+                let child_stdout = child.stdout.take().expect("stdout is piped");
+                let child_stderr = child.stderr.take().expect("stderr is piped");
+
+                let (stdout, stderr, status) = tokio::try_join!(
+                    tee(child_stdout, io::stdout()),
+                    tee(child_stderr, io::stderr()),
+                    async { child.wait().await.into_diagnostic() }
+                )?;
+
+                Ok(Output {
+                    status,
+                    stdout,
+                    stderr,
+                }
+                .into())
+            }
+            _ => self.output().await,
+        }
+    }
+
     /// Returns whether the given command is installed.
     pub async fn exists<D: Display>(cmd: D) -> bool {
         Self::new(format!("{cmd} --version"))
@@ -60,6 +161,39 @@ impl WCommand {
     }
 }
 
+/// How to handle the output of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutput {
+    /// Output is inherited from the current process.
+    Inherit,
+    /// Output is captured and not shown.
+    Capture,
+    /// Output is both shown and captured, e.g. to parse the output while showing the progress.
+    Tee,
+}
+
+/// Write everything that is read from the reader to the writer and return the read bytes.
+async fn tee<R, W>(mut reader: R, mut writer: W) -> miette::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut captured = vec![];
+    let mut buf = [0; 4096];
+    loop {
+        let len = reader.read(&mut buf).await.into_diagnostic()?;
+        if len == 0 {
+            break;
+        }
+
+        writer.write_all(&buf[..len]).await.into_diagnostic()?;
+        writer.flush().await.into_diagnostic()?;
+        captured.extend_from_slice(&buf[..len]);
+    }
+
+    Ok(captured)
+}
+
 /// Utility struct for [`Output`].
 pub struct ReadableOutput(Output);
 
@@ -79,6 +213,18 @@ impl ReadableOutput {
         Self::convert(&self.0.stdout)
     }
 
+    /// Get the stdout followed by the stderr, or `None` if both are empty, e.g the output was
+    /// inherited.
+    pub fn captured(&self) -> Option<String> {
+        let captured = [self.stdout(), self.stderr()]
+            .into_iter()
+            .map(|output| output.trim_end_matches('\n'))
+            .filter(|output| !output.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!captured.is_empty()).then_some(captured)
+    }
+
     /// Convert the given bytes to UTF-8.
     fn convert(bytes: &[u8]) -> &str {
         std::str::from_utf8(bytes).unwrap_or_default()
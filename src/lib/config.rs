@@ -0,0 +1,273 @@
+//! Configuration read from the `watchso.toml` file at the project root.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use miette::IntoDiagnostic;
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::{
+    constants::{dirname, filename},
+    hook::HookKind,
+};
+
+/// Watch configuration.
+///
+/// All fields are optional and the default configuration is used when the config file doesn't
+/// exist.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Build configuration.
+    pub build: BuildConfig,
+    /// Control server configuration.
+    pub control: ControlConfig,
+    /// Deploy configuration.
+    pub deploy: DeployConfig,
+    /// Shell command hooks.
+    pub hooks: HooksConfig,
+    /// IDL configuration.
+    pub idl: IdlConfig,
+    /// Program logs configuration.
+    pub logs: LogsConfig,
+    /// Program error decoding configuration.
+    pub errors: ErrorsConfig,
+    /// ELF size and compute unit reporting configuration.
+    pub report: ReportConfig,
+    /// Account fixtures configuration.
+    pub fixtures: FixturesConfig,
+}
+
+impl Config {
+    /// Read and parse the config file at the given project directory.
+    ///
+    /// Returns the default configuration if the config file doesn't exist.
+    pub async fn read<P: AsRef<Path>>(origin: P) -> miette::Result<Self> {
+        let config_path = origin.as_ref().join(filename::WATCHSO_TOML);
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        toml::from_str(&fs::read_to_string(config_path).await.into_diagnostic()?).into_diagnostic()
+    }
+}
+
+/// Control server configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlConfig {
+    /// Whether to start the control server.
+    pub enabled: bool,
+    /// Path of the Unix domain socket, relative to the project root.
+    ///
+    /// Default is `target/watchso.sock`.
+    pub socket_path: Option<PathBuf>,
+}
+
+impl ControlConfig {
+    /// Get the absolute path of the Unix domain socket.
+    pub fn get_socket_path<P: AsRef<Path>>(&self, origin: P) -> PathBuf {
+        origin.as_ref().join(
+            self.socket_path
+                .clone()
+                .unwrap_or_else(|| Path::new(dirname::TARGET).join(filename::WATCHSO_SOCK)),
+        )
+    }
+}
+
+/// Account fixtures configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixturesConfig {
+    /// Directory of the account JSON files, relative to the project root.
+    ///
+    /// Default is `fixtures`.
+    pub dir: Option<PathBuf>,
+    /// Addresses of the accounts to snapshot when no address is given to `watchso snapshot`.
+    pub accounts: Vec<String>,
+    /// Shell command that seeds the accounts after all programs are deployed to a new or reset
+    /// test validator.
+    pub seed: Option<String>,
+}
+
+impl FixturesConfig {
+    /// Get the absolute path of the fixtures directory.
+    pub fn get_dir<P: AsRef<Path>>(&self, origin: P) -> PathBuf {
+        origin.as_ref().join(
+            self.dir
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(dirname::FIXTURES)),
+        )
+    }
+}
+
+/// Build configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    /// Whether to fail the build and block the deployment when the build output has stack offset
+    /// warnings.
+    pub deny_stack_warnings: bool,
+    /// Whether to switch to the Anchor and Solana versions of the `[toolchain]` table in
+    /// `Anchor.toml` with `avm use` and `agave-install init` before building.
+    pub switch_toolchain: bool,
+    /// Build configurations of the Native programs by the program names.
+    pub programs: HashMap<String, ProgramBuildConfig>,
+}
+
+/// Build configuration of a Native program.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProgramBuildConfig {
+    /// Cargo features to activate, e.g. `localnet`.
+    pub features: Vec<String>,
+    /// Whether to deactivate the default features.
+    pub no_default_features: bool,
+    /// Output directory of the ELF and the program keypair, relative to the project root.
+    ///
+    /// They are copied to `target/deploy` after the build to be deployed.
+    pub sbf_out_dir: Option<PathBuf>,
+    /// Platform tools version, e.g. `v1.41`.
+    pub tools_version: Option<String>,
+    /// SBF architecture, e.g. `v1`.
+    pub arch: Option<String>,
+    /// Environment variables of the build command.
+    pub env: HashMap<String, String>,
+}
+
+/// Deploy configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeployConfig {
+    /// Whether to close the program when its ELF file is removed.
+    ///
+    /// This reclaims the program's lamports on the test validator but the program id can't be
+    /// used again until the validator is reset.
+    pub close_on_elf_removal: bool,
+    /// Whether to close the buffer account of a failed Native deployment to reclaim its lamports.
+    ///
+    /// The buffer is kept otherwise, and the next deployment resumes writing to it. A new buffer is
+    /// used instead if the ELF has grown past the size of the kept buffer. If the kept buffer
+    /// can't be closed, its keypair is moved to `target/buffers/<BUFFER>.json`.
+    pub close_failed_buffers: bool,
+    /// Name of the `[scripts]` entry of `Anchor.toml` to run after the deployments, or `migrate`
+    /// to run `anchor migrate`, e.g. to seed the program state.
+    ///
+    /// It runs once per batch of deployments, e.g. once after the initial deployment of all
+    /// programs, with the deployed program names in the `WATCHSO_PROGRAMS` environment variable.
+    pub anchor_script: Option<String>,
+}
+
+/// IDL configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdlConfig {
+    /// Whether to upgrade the on-chain IDL after a deployment when the IDL has changed.
+    ///
+    /// The IDL is initialized if it doesn't exist on-chain.
+    pub upgrade: bool,
+    /// Directories to copy the IDLs and their TypeScript types to after a build, relative to the
+    /// project root.
+    pub client_dirs: Vec<PathBuf>,
+    /// Whether to fail the build and block the deployment when the IDL has breaking changes, e.g.
+    /// removed or reordered account fields. They are reported as warnings otherwise.
+    pub deny_breaking_changes: bool,
+    /// Shell command that generates the clients from the IDL, e.g. a Codama script.
+    ///
+    /// It runs at the project root after a successful build when the IDL content has changed,
+    /// with the `WATCHSO_PROGRAM` and `WATCHSO_IDL_PATH` environment variables.
+    pub generator: Option<String>,
+    /// Whether to generate the IDLs of the Native programs that depend on `shank` with
+    /// `shank idl` after a build.
+    pub shank: bool,
+}
+
+/// Program logs configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Whether to start streaming the program logs after initialization.
+    pub enabled: bool,
+    /// Websocket URL of the validator.
+    ///
+    /// Default is the websocket URL of the Solana CLI config.
+    pub websocket_url: Option<String>,
+}
+
+/// Program error decoding configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ErrorsConfig {
+    /// Rust files that define the custom error enum of the programs by the program name, relative
+    /// to the project root.
+    ///
+    /// Programs with an IDL in `target/idl` don't need this.
+    pub sources: HashMap<String, PathBuf>,
+}
+
+/// ELF size and compute unit reporting configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    /// ELF sizes in bytes to warn about when a program's ELF grows past them.
+    pub elf_size_thresholds: Vec<u64>,
+    /// Whether to report the consumed compute units of each instruction from the streamed program
+    /// logs.
+    pub compute_units: bool,
+}
+
+/// Shell commands to run on watch events.
+///
+/// Commands run with `sh -c` in the project root and the event details are passed as environment
+/// variables prefixed with `WATCHSO_`. A failing `before_*` command cancels the operation.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Command to run when watched files have changed.
+    pub on_change_detected: Option<String>,
+    /// Command to run before a program is built.
+    pub before_build: Option<String>,
+    /// Command to run after a program is built.
+    pub after_build: Option<String>,
+    /// Command to run before a program is deployed.
+    pub before_deploy: Option<String>,
+    /// Command to run after a program is deployed.
+    pub after_deploy: Option<String>,
+    /// Command to run after a program id is updated.
+    pub on_program_id_updated: Option<String>,
+    /// Command to run when a program logs a transaction.
+    pub on_program_log: Option<String>,
+    /// Command to run when the IDL of a program has changed after a build.
+    pub on_idl_changed: Option<String>,
+    /// Command to run after the ELF size of a program is measured.
+    pub on_elf_size: Option<String>,
+    /// Command to run when an instruction's consumed compute units are measured.
+    pub on_compute_units: Option<String>,
+    /// Command to run when a warning occurs.
+    pub on_warning: Option<String>,
+    /// Command to run when an error occurs.
+    pub on_error: Option<String>,
+}
+
+impl HooksConfig {
+    /// Get the command of the given hook kind.
+    pub fn get(&self, kind: HookKind) -> Option<&str> {
+        match kind {
+            HookKind::ChangeDetected => self.on_change_detected.as_deref(),
+            HookKind::BeforeBuild => self.before_build.as_deref(),
+            HookKind::AfterBuild => self.after_build.as_deref(),
+            HookKind::BeforeDeploy => self.before_deploy.as_deref(),
+            HookKind::AfterDeploy => self.after_deploy.as_deref(),
+            HookKind::ProgramIdUpdated => self.on_program_id_updated.as_deref(),
+            HookKind::ProgramLog => self.on_program_log.as_deref(),
+            HookKind::IdlChanged => self.on_idl_changed.as_deref(),
+            HookKind::ElfSize => self.on_elf_size.as_deref(),
+            HookKind::ComputeUnits => self.on_compute_units.as_deref(),
+            HookKind::Warning => self.on_warning.as_deref(),
+            HookKind::Error => self.on_error.as_deref(),
+        }
+    }
+}
@@ -6,8 +6,14 @@ pub mod filename {
     pub const CARGO_TOML: &str = "Cargo.toml";
     /// Anchor manifest file
     pub const ANCHOR_TOML: &str = "Anchor.toml";
+    /// Watchso configuration file
+    pub const WATCHSO_TOML: &str = "watchso.toml";
+    /// Control server socket file
+    pub const WATCHSO_SOCK: &str = "watchso.sock";
     /// Starting point of a Rust library
     pub const LIB_RS: &str = "lib.rs";
+    /// Generated Rust module of a Seahorse program's Python file
+    pub const PROGRAM_RS: &str = "program.rs";
 }
 
 /// Directory name constants.
@@ -18,8 +24,22 @@ pub mod dirname {
     pub const TARGET: &str = "target";
     /// `deploy` directory under `target` folder
     pub const DEPLOY: &str = "deploy";
+    /// `idl` directory under `target` folder
+    pub const IDL: &str = "idl";
+    /// `types` directory under `target` folder that includes the TypeScript types of the IDLs
+    pub const TYPES: &str = "types";
+    /// `buffers` directory under `target` folder that includes the deploy buffer keypairs
+    pub const BUFFERS: &str = "buffers";
     /// `programs_py` directory for Seahorse programs
     pub const PROGRAMS_PY: &str = "programs_py";
+    /// `fixtures` directory that includes the account JSON files
+    pub const FIXTURES: &str = "fixtures";
+    /// `programs` directory that includes the generated Rust code of Seahorse programs
+    pub const PROGRAMS: &str = "programs";
+    /// `dot` directory of the generated Seahorse programs that includes the generated modules
+    pub const DOT: &str = "dot";
+    /// `test-ledger` directory of `solana-test-validator`
+    pub const TEST_LEDGER: &str = "test-ledger";
 }
 
 /// File extension constants.
@@ -34,6 +54,8 @@ pub mod extension {
     pub const JSON: &str = "json";
     /// Python extension
     pub const PY: &str = "py";
+    /// TypeScript extension
+    pub const TS: &str = "ts";
 }
 
 /// Emoji constants.
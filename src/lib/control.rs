@@ -0,0 +1,318 @@
+//! Control the running watch process.
+//!
+//! Other tools can send [`ControlRequest`]s to the control server over a Unix domain socket. The
+//! protocol is line based, each line is a JSON encoded request or response.
+
+#[cfg(unix)]
+use std::{path::Path, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use clap::Subcommand;
+#[cfg(unix)]
+use miette::IntoDiagnostic;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::{
+    command::CommandOutput,
+    error::WatchError,
+    framework::WatchableFramework,
+    framework_utils::{get_pubkey_from_keypair_path, ProgramName},
+};
+
+/// Whether watching is paused.
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether watching is paused.
+///
+/// Changes are ignored while watching is paused.
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Pause or resume watching.
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+}
+
+/// Request to the control server.
+#[derive(Debug, Clone, Serialize, Deserialize, Subcommand)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Get the status of the watch process and the programs
+    Status,
+    /// Build the program, or all programs if the program name is not specified
+    Build {
+        /// Program name
+        program: Option<String>,
+    },
+    /// Deploy the program, or all programs if the program name is not specified
+    Deploy {
+        /// Program name
+        program: Option<String>,
+    },
+    /// Update the program id, or all program ids if the program name is not specified
+    UpdateProgramId {
+        /// Program name
+        program: Option<String>,
+    },
+    /// Pause watching, changes are ignored until watching is resumed
+    Pause,
+    /// Resume watching
+    Resume,
+}
+
+/// Response of the control server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ControlResponse {
+    /// Request was successful.
+    Ok,
+    /// Status of the watch process.
+    Status(WatchStatus),
+    /// Request has failed.
+    Error {
+        /// Error message
+        message: String,
+    },
+}
+
+/// Status of the watch process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    /// Whether watching is paused
+    pub paused: bool,
+    /// Status of the programs
+    pub programs: Vec<ProgramStatus>,
+}
+
+/// Status of a program.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgramStatus {
+    /// Program name
+    pub name: String,
+    /// Root directory of the program
+    pub path: PathBuf,
+    /// Program id, only available if the program keypair exists
+    pub program_id: Option<String>,
+}
+
+/// Handle the request by dispatching it into the framework.
+pub async fn dispatch(
+    framework: &dyn WatchableFramework,
+    request: ControlRequest,
+) -> ControlResponse {
+    let result = match request {
+        ControlRequest::Status => get_status(framework).await.map(ControlResponse::Status),
+        ControlRequest::Build { program } => build(framework, program).await,
+        ControlRequest::Deploy { program } => deploy(framework, program).await,
+        ControlRequest::UpdateProgramId { program } => update_program_id(framework, program).await,
+        ControlRequest::Pause => {
+            set_paused(true);
+            Ok(ControlResponse::Ok)
+        }
+        ControlRequest::Resume => {
+            set_paused(false);
+            Ok(ControlResponse::Ok)
+        }
+    };
+
+    result.unwrap_or_else(|err| ControlResponse::Error {
+        message: err.to_string(),
+    })
+}
+
+/// Get the status of the watch process and the programs.
+async fn get_status(framework: &dyn WatchableFramework) -> miette::Result<WatchStatus> {
+    let mut programs = vec![];
+    for (name, path) in framework.project_map().get_all().await {
+        let keypair_path = framework
+            .deploy_path()
+            .join(ProgramName::new(&name).keypair_file_name());
+        let program_id = match keypair_path.exists() {
+            true => get_pubkey_from_keypair_path(keypair_path).await.ok(),
+            false => None,
+        };
+
+        programs.push(ProgramStatus {
+            name,
+            path,
+            program_id,
+        });
+    }
+    programs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(WatchStatus {
+        paused: is_paused(),
+        programs,
+    })
+}
+
+/// Build the program or all programs.
+async fn build(
+    framework: &dyn WatchableFramework,
+    program: Option<String>,
+) -> miette::Result<ControlResponse> {
+    for (_, program_path) in get_programs(framework, program).await? {
+        if !framework
+            .run_build(&program_path, CommandOutput::Inherit)
+            .await?
+        {
+            return Err(WatchError::CommandFailed("build"))?;
+        }
+    }
+
+    Ok(ControlResponse::Ok)
+}
+
+/// Deploy the program or all programs.
+async fn deploy(
+    framework: &dyn WatchableFramework,
+    program: Option<String>,
+) -> miette::Result<ControlResponse> {
+    let mut deployed_elf_paths = vec![];
+    let mut result = Ok(ControlResponse::Ok);
+    for (name, _) in get_programs(framework, program).await? {
+        let elf_path = framework
+            .deploy_path()
+            .join(ProgramName::new(name).elf_file_name());
+        if !elf_path.exists() {
+            result = Err(WatchError::FileNotFound(elf_path).into());
+            break;
+        }
+
+        if !framework
+            .run_deploy(&elf_path, CommandOutput::Inherit)
+            .await?
+        {
+            result = Err(WatchError::CommandFailed("deploy").into());
+            break;
+        }
+        deployed_elf_paths.push(elf_path);
+    }
+
+    framework.post_deploy_batch(&deployed_elf_paths).await?;
+    result
+}
+
+/// Update the program id of the program or all programs.
+async fn update_program_id(
+    framework: &dyn WatchableFramework,
+    program: Option<String>,
+) -> miette::Result<ControlResponse> {
+    for (name, _) in get_programs(framework, program).await? {
+        let keypair_path = framework
+            .deploy_path()
+            .join(ProgramName::new(name).keypair_file_name());
+        if !keypair_path.exists() {
+            return Err(WatchError::FileNotFound(keypair_path))?;
+        }
+
+        framework.run_update_program_id(&keypair_path).await?;
+    }
+
+    Ok(ControlResponse::Ok)
+}
+
+/// Get the program with the given name, or all programs if the name is not specified.
+async fn get_programs(
+    framework: &dyn WatchableFramework,
+    program: Option<String>,
+) -> miette::Result<Vec<(String, PathBuf)>> {
+    match program {
+        Some(name) => match framework
+            .project_map()
+            .get_program_path_from_name(&name)
+            .await
+        {
+            Some(program_path) => Ok(vec![(name, program_path)]),
+            None => Err(WatchError::ProgramNotFound(name))?,
+        },
+        None => Ok(framework
+            .project_map()
+            .get_all()
+            .await
+            .into_iter()
+            .collect()),
+    }
+}
+
+/// Start the control server at the given Unix domain socket path.
+///
+/// The socket file is replaced if it already exists. This function only returns on error.
+#[cfg(unix)]
+pub async fn serve<P: AsRef<Path>>(
+    framework: Arc<dyn WatchableFramework>,
+    socket_path: P,
+) -> miette::Result<()> {
+    if socket_path.as_ref().exists() {
+        fs::remove_file(&socket_path).await.into_diagnostic()?;
+    }
+    if let Some(parent) = socket_path.as_ref().parent() {
+        fs::create_dir_all(parent).await.into_diagnostic()?;
+    }
+
+    let listener = UnixListener::bind(&socket_path).into_diagnostic()?;
+    loop {
+        let (stream, _) = listener.accept().await.into_diagnostic()?;
+        let framework = framework.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let response = match serde_json::from_str(&line) {
+                    Ok(request) => dispatch(framework.as_ref(), request).await,
+                    Err(err) => ControlResponse::Error {
+                        message: err.to_string(),
+                    },
+                };
+
+                let Ok(mut response) = serde_json::to_string(&response) else {
+                    break;
+                };
+                response.push('\n');
+                if writer.write_all(response.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Send the request to the control server at the given Unix domain socket path.
+#[cfg(unix)]
+pub async fn send<P: AsRef<Path>>(
+    socket_path: P,
+    request: &ControlRequest,
+) -> miette::Result<ControlResponse> {
+    let stream = UnixStream::connect(&socket_path)
+        .await
+        .map_err(|_| WatchError::ControlServerNotRunning(socket_path.as_ref().into()))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut request = serde_json::to_string(request).into_diagnostic()?;
+    request.push('\n');
+    writer
+        .write_all(request.as_bytes())
+        .await
+        .into_diagnostic()?;
+
+    let response = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .into_diagnostic()?
+        .ok_or(WatchError::ControlServerNotRunning(
+            socket_path.as_ref().into(),
+        ))?;
+
+    serde_json::from_str(&response).into_diagnostic()
+}
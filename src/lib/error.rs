@@ -19,4 +19,44 @@ pub enum WatchError {
     /// This most likely happens when the keypair file is not in a valid form.
     #[error("Could not get keypair file: `{0}`")]
     CouldNotGetKeypair(String),
+
+    /// Shell command hook exited with a non-zero status.
+    #[error("Hook failed: `{0}`")]
+    HookFailed(&'static str),
+
+    /// Program doesn't exist in the mapped programs.
+    #[error("Program not found: `{0}`")]
+    ProgramNotFound(String),
+
+    /// File doesn't exist, e.g. the program hasn't been built yet.
+    #[error("File not found: `{0}`")]
+    FileNotFound(PathBuf),
+
+    /// Command exited with a non-zero status.
+    #[error("Command failed: `{0}`")]
+    CommandFailed(&'static str),
+
+    /// Anchor program that Seahorse generates doesn't exist.
+    #[error("Generated program not found: `{0}`")]
+    #[diagnostic(help("Build the Seahorse program to generate the Anchor program"))]
+    GeneratedProgramNotFound(PathBuf),
+
+    /// Client generator command exited with a non-zero status.
+    #[error("Client generator failed: {0}")]
+    GeneratorFailed(String),
+
+    /// Control server is not running at the given socket path.
+    #[error("Control server is not running at `{0}`")]
+    #[diagnostic(help("Enable the control server with `watchso --control`"))]
+    ControlServerNotRunning(PathBuf),
+
+    /// Running test validator was not started by this process, so it can't be restarted.
+    #[error("Test validator was not started by watchso")]
+    #[diagnostic(help("Stop the running test validator and restart watchso"))]
+    TestValidatorNotOwned,
+
+    /// No account addresses are given to snapshot.
+    #[error("No accounts to snapshot")]
+    #[diagnostic(help("Pass the addresses or set `fixtures.accounts` in the config file"))]
+    NoAccountsToSnapshot,
 }
@@ -0,0 +1,149 @@
+//! Account fixtures of the test validator.
+//!
+//! Fixtures are account JSON files in the format of `solana account --output json`. They are
+//! loaded into the ledger when the test validator is started by this process, and they can be
+//! snapshotted from the running test validator with `watchso snapshot`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use miette::IntoDiagnostic;
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::{
+    command::WCommand,
+    config::FixturesConfig,
+    constants::extension,
+    error::WatchError,
+    framework::Framework,
+    output::{print_error, print_info, print_warning},
+};
+
+/// Whether the seed command has run against the current test validator.
+static SEEDED: AtomicBool = AtomicBool::new(false);
+
+/// Run the seed command again after the next deployment, e.g. after the ledger is reset.
+pub fn reset_seeded() {
+    SEEDED.store(false, Ordering::Relaxed);
+}
+
+/// Account JSON file that `solana account --output json` outputs.
+#[derive(Deserialize)]
+struct AccountFile {
+    /// Address of the account
+    pubkey: String,
+}
+
+/// Get the `--account <ADDRESS> <PATH>` arguments of `solana-test-validator` for the account
+/// fixtures.
+///
+/// Files that are not valid account JSON files are skipped with a warning.
+pub async fn get_validator_args<P: AsRef<Path>>(origin: P, config: &FixturesConfig) -> Vec<String> {
+    let mut args = vec![];
+    for path in get_fixture_paths(config.get_dir(origin)).await {
+        let account = fs::read_to_string(&path)
+            .await
+            .ok()
+            .and_then(|content| serde_json::from_str::<AccountFile>(&content).ok());
+        match account {
+            Some(account) => args.extend([
+                "--account".to_owned(),
+                account.pubkey,
+                path.display().to_string(),
+            ]),
+            None => print_warning(format!(
+                "Skipping invalid account fixture: `{}`",
+                path.display()
+            )),
+        }
+    }
+
+    args
+}
+
+/// Run the seed command of the config once per test validator session.
+///
+/// A failing seed command is reported as a warning and the command runs again after the next
+/// deployment.
+pub async fn seed<F: Framework + ?Sized>(framework: &F) -> miette::Result<()> {
+    let Some(seed) = framework.config().fixtures.seed.as_ref() else {
+        return Ok(());
+    };
+    if SEEDED.swap(true, Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let output = WCommand::shell(seed)
+        .current_dir(framework.origin())
+        .output()
+        .await?;
+    if output.status().success() {
+        print_info("Seeded the accounts");
+        return Ok(());
+    }
+
+    reset_seeded();
+    let mut message = format!("Seed command failed: `{seed}`");
+    if let Some(captured) = output.captured() {
+        message.push('\n');
+        message.push_str(&captured);
+    }
+
+    framework.warn(message).await
+}
+
+/// Save the given accounts of the running test validator to the fixtures directory.
+///
+/// Accounts of the config are saved if no address is given.
+pub async fn snapshot<P: AsRef<Path>>(
+    origin: P,
+    config: &FixturesConfig,
+    addresses: Vec<String>,
+) -> miette::Result<()> {
+    let addresses = match addresses.is_empty() {
+        true => config.accounts.clone(),
+        false => addresses,
+    };
+    if addresses.is_empty() {
+        return Err(WatchError::NoAccountsToSnapshot)?;
+    }
+
+    let dir = config.get_dir(origin);
+    fs::create_dir_all(&dir).await.into_diagnostic()?;
+
+    for address in addresses {
+        let path = dir.join(&address).with_extension(extension::JSON);
+        let output = WCommand::new(format!("solana account {address} --output json"))
+            .arg("--output-file")
+            .arg(&path)
+            .output()
+            .await?;
+        if !output.status().success() {
+            print_error(output.stderr().trim_end());
+            return Err(WatchError::CommandFailed("solana account"))?;
+        }
+
+        print_info(format!("Saved `{address}` to `{}`", path.display()));
+    }
+
+    Ok(())
+}
+
+/// Get the paths of the JSON files in the fixtures directory, sorted by path.
+async fn get_fixture_paths<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    if let Ok(mut entries) = fs::read_dir(dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some(extension::JSON) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+
+    paths
+}
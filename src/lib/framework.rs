@@ -4,22 +4,33 @@ use std::{
     collections::HashSet,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Instant,
 };
 
 use async_trait::async_trait;
 use miette::IntoDiagnostic;
-use tokio::fs;
+use tokio::{fs, sync::Mutex};
 use watchexec::filter::Filterer;
 
 use crate::{
-    action::WAction,
-    command::WCommand,
-    constants::{dirname, extension},
+    action::{PathChange, WAction},
+    command::{CommandOutput, WCommand},
+    config::Config,
+    constants::{dirname, extension, filename},
+    error::WatchError,
+    fixtures,
     framework_utils::{
-        create_globset_filterer, find_and_update_program_id, get_program_path, get_watch_pathset,
-        start_test_validator,
+        create_globset_filterer, find_and_update_program_id, get_program_id, get_program_path,
+        get_pubkey_from_keypair_path, get_watch_pathset, is_deploy_blocked, parse_deploy_output,
+        set_deploy_blocked, set_program_id, start_test_validator, ProgramName, ProjectMap,
     },
+    hook::{HookEvent, Hooks},
+    idl,
+    output::{print_diagnostic, print_error, print_warning, OutputFormat},
+    program_error::ErrorDecoder,
     progress::Progress,
+    report,
+    sbf::parse_stack_warnings,
 };
 
 /// Watchable Solana program framework.
@@ -39,6 +50,26 @@ pub trait WatchableFramework: Framework + Send + Sync {
         get_watch_pathset(self.origin()).await
     }
 
+    /// Paths that affect program discovery, e.g. the root manifest file.
+    ///
+    /// Changes to these paths re-run [`Framework::map_program_names`] and the pathset is
+    /// reconfigured if the programs have changed, so programs can be added, removed or renamed
+    /// while watching.
+    ///
+    /// Default implementation is for Rust.
+    fn discovery_paths(&self) -> Vec<PathBuf> {
+        vec![self.origin().join(filename::CARGO_TOML)]
+    }
+
+    /// Returns whether the given path is one of the [`WatchableFramework::discovery_paths`] or
+    /// is inside of one of them.
+    fn is_discovery_path(&self, path: &Path) -> bool {
+        let path = self.origin().join(path);
+        self.discovery_paths()
+            .iter()
+            .any(|discovery_path| path.starts_with(discovery_path))
+    }
+
     /// Filterer implementation that filters events.
     ///
     /// Default implementation is for Rust.
@@ -62,26 +93,111 @@ pub trait WatchableFramework: Framework + Send + Sync {
         // Saving unique program paths because multiple files can be modified within the same
         // action. This way, we don't rebuild the same program in the same action.
         let mut unique_program_paths = HashSet::new();
-        for action_path in action.get_unique_paths() {
+        let mut deployed_elf_paths = vec![];
+        for (action_path, change) in action.get_unique_path_changes() {
+            if self.is_discovery_path(action_path) {
+                unique_program_paths.extend(self.refresh_programs().await?);
+
+                // Only rebuild if the manifest also belongs to a program, e.g. the origin is not
+                // a workspace
+                let is_program_manifest = match action_path.parent() {
+                    Some(parent) => {
+                        self.project_map()
+                            .contains_program_path(self.origin().join(parent))
+                            .await
+                    }
+                    None => false,
+                };
+                if !is_program_manifest {
+                    continue;
+                }
+            }
+
             if let Some(ext) = action_path.extension().and_then(|ext| ext.to_str()) {
                 match ext {
                     extension::RS | extension::TOML => {
-                        let program_path = get_program_path(action_path).await?;
-                        unique_program_paths.insert(program_path);
+                        if change.is_removal() {
+                            // The directory of the removed file might not exist anymore, get the
+                            // program from the mapped programs instead of the filesystem
+                            let program_path = self
+                                .project_map()
+                                .get_program_path_from_file_path(self.origin().join(action_path))
+                                .await
+                                .filter(|path| path.join(filename::CARGO_TOML).exists());
+                            unique_program_paths.extend(program_path);
+                        } else {
+                            let program_path = get_program_path(action_path).await?;
+                            unique_program_paths.insert(program_path);
+                        }
                     }
                     extension::SO => {
-                        self.deploy(action_path).await.spawn().await?;
-                    }
-                    extension::JSON => {
-                        self.update_program_id(action_path).await?;
+                        let deployed = self.on_elf_change(action_path, change).await?;
+                        if deployed {
+                            deployed_elf_paths.push(action_path.to_path_buf());
+                        }
                     }
+                    extension::JSON => self.on_keypair_change(action_path, change).await?,
                     _ => (),
                 }
             }
         }
 
+        self.post_deploy_batch(&deployed_elf_paths).await?;
+
         for program_path in unique_program_paths {
-            self.build(&program_path).await.spawn().await?;
+            self.run_build(&program_path, CommandOutput::Inherit)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Callback to run when a program ELF file has changed.
+    ///
+    /// Deploys the program if the ELF is created or modified. If the ELF is removed, the program
+    /// is closed only if [`DeployConfig::close_on_elf_removal`] is enabled.
+    ///
+    /// Returns whether the program was deployed successfully.
+    ///
+    /// [`DeployConfig::close_on_elf_removal`]: crate::config::DeployConfig::close_on_elf_removal
+    async fn on_elf_change(&self, elf_path: &Path, change: PathChange) -> miette::Result<bool> {
+        if !change.is_removal() {
+            return self.run_deploy(elf_path, CommandOutput::Inherit).await;
+        }
+        if self.config().deploy.close_on_elf_removal {
+            self.run_close(elf_path).await?;
+        }
+
+        Ok(false)
+    }
+
+    /// Callback to run when a program keypair file has changed.
+    ///
+    /// Updates the program id if the keypair is created or modified, warns if it's removed.
+    /// Removals of other JSON files, e.g. IDLs, are ignored.
+    async fn on_keypair_change(
+        &self,
+        program_keypair_path: &Path,
+        change: PathChange,
+    ) -> miette::Result<()> {
+        if change.is_removal() {
+            let is_program_keypair = ProgramName::from_keypair_path(program_keypair_path).is_some()
+                && self
+                    .origin()
+                    .join(program_keypair_path)
+                    .starts_with(self.deploy_path());
+            if !is_program_keypair {
+                return Ok(());
+            }
+
+            self.warn(format!(
+                "Program keypair removed: `{}`. A new program id will be used after the next \
+                build.",
+                program_keypair_path.display()
+            ))
+            .await?;
+        } else {
+            self.run_update_program_id(program_keypair_path).await?;
         }
 
         Ok(())
@@ -94,6 +210,35 @@ pub trait Framework: Send + Sync {
     /// Origin is the root directory of the project and other paths will be derived from this path.
     fn origin(&self) -> &Path;
 
+    /// Configuration of the project.
+    fn config(&self) -> &Config;
+
+    /// Path of the `target/deploy` directory that includes the program keypairs and ELFs.
+    fn deploy_path(&self) -> PathBuf {
+        self.origin().join(dirname::TARGET).join(dirname::DEPLOY)
+    }
+
+    /// Path of the `target/idl` directory that includes the program IDLs.
+    fn idl_path(&self) -> PathBuf {
+        self.origin().join(dirname::TARGET).join(dirname::IDL)
+    }
+
+    /// Hooks that run on watch events.
+    fn hooks(&self) -> &Hooks;
+
+    /// Emit the event to the [`Hooks`], including the configured shell command hooks.
+    async fn emit(&self, event: HookEvent) -> miette::Result<()> {
+        self.hooks()
+            .emit(&event, &self.config().hooks, self.origin())
+            .await
+    }
+
+    /// Print the warning and emit [`HookEvent::Warning`].
+    async fn warn(&self, message: String) -> miette::Result<()> {
+        print_warning(&message);
+        self.emit(HookEvent::Warning { message }).await
+    }
+
     /// Handle the necessary checks and initialize the framework.
     ///
     /// This is called before watching starts.
@@ -105,18 +250,23 @@ pub trait Framework: Send + Sync {
             .message("Starting Solana test validator...")
             .success_message("Running Solana test validator")
             .error_message("Could not start Solana test validator")
-            .spinner_with(|| async { start_test_validator(self.origin()).await })
+            .spinner_with(|| async { start_test_validator(self.origin(), self.config()).await })
             .await?;
 
         // If `target/deploy` doesn't exist, build the programs first to create the program keypair
         // and program ELF
-        let deploy_path = self.origin().join(dirname::TARGET).join(dirname::DEPLOY);
+        let deploy_path = self.deploy_path();
         if !deploy_path.exists() {
             Progress::new()
                 .message("Setting up...")
                 .success_message("Setup success")
                 .error_message("Setup error")
-                .spinner_with(|| async { self.build(self.origin()).await.output().await })
+                .spinner_with(|| async {
+                    for build_path in self.setup_build_paths().await {
+                        self.run_build(&build_path, CommandOutput::Capture).await?;
+                    }
+                    Ok(())
+                })
                 .await?;
         }
 
@@ -148,7 +298,7 @@ pub trait Framework: Send + Sync {
             .success_message("Program ids are up to date")
             .error_message("Couldn't update program ids")
             .progress_with(keypair_paths, |keypair_path| async move {
-                self.update_program_id(&keypair_path).await
+                self.run_update_program_id(&keypair_path).await
             })
             .await?;
 
@@ -157,20 +307,37 @@ pub trait Framework: Send + Sync {
             .success_message("Built programs")
             .error_message("Couldn't build programs")
             .progress_with(unique_build_paths, |build_path| async move {
-                self.build(&build_path).await.output().await
+                self.run_build(&build_path, CommandOutput::Capture).await
             })
             .await?;
 
+        let elf_count = elf_paths.len();
+        let deployed_elf_paths = Mutex::new(vec![]);
         Progress::new()
             .message("Deploying programs...")
             .success_message("Deployed programs")
             .error_message("Couldn't deploy programs")
-            .progress_with(elf_paths, |elf_path| async move {
-                self.deploy(&elf_path).await.output().await
+            .progress_with(elf_paths, |elf_path| {
+                let deployed_elf_paths = &deployed_elf_paths;
+                async move {
+                    if self.run_deploy(&elf_path, CommandOutput::Capture).await? {
+                        deployed_elf_paths.lock().await.push(elf_path);
+                    }
+                    Ok(())
+                }
             })
             .await?;
+        let deployed_elf_paths = deployed_elf_paths.into_inner();
+        self.post_deploy_batch(&deployed_elf_paths).await?;
 
-        println!();
+        // Seeding depends on the programs, skip it until all of them are deployed
+        if deployed_elf_paths.len() == elf_count {
+            fixtures::seed(self).await?;
+        }
+
+        if OutputFormat::is_human() {
+            println!();
+        }
 
         Ok(())
     }
@@ -178,29 +345,339 @@ pub trait Framework: Send + Sync {
     /// Check the installed toolsets, e.g Solana CLI.
     async fn check_toolset(&self) -> miette::Result<()>;
 
+    /// Mapping of the program names and their paths.
+    fn project_map(&self) -> &ProjectMap;
+
     /// Read and cache the program names with their paths to not use filesystem on every action.
+    ///
+    /// This is called again whenever program discovery should be refreshed, which means stale
+    /// programs should be removed from the [`ProjectMap`].
     async fn map_program_names(&self) -> miette::Result<()>;
 
+    /// Re-run the program discovery.
+    ///
+    /// Returns the paths of the newly added programs.
+    async fn refresh_programs(&self) -> miette::Result<Vec<PathBuf>> {
+        let previous_program_hm = self.project_map().get_all().await;
+        self.map_program_names().await?;
+
+        let added_program_paths = self
+            .project_map()
+            .get_all()
+            .await
+            .into_iter()
+            .filter(|(name, _)| !previous_program_hm.contains_key(name))
+            .map(|(_, path)| path)
+            .collect();
+
+        Ok(added_program_paths)
+    }
+
+    /// Get the program names and the ELF paths of the programs inside the given directory.
+    async fn get_elf_paths(&self, path: &Path) -> Vec<(String, PathBuf)> {
+        self.project_map()
+            .get_all()
+            .await
+            .into_iter()
+            .filter(|(_, program_path)| program_path.starts_with(path))
+            .map(|(name, _)| {
+                let elf_path = self
+                    .deploy_path()
+                    .join(ProgramName::new(&name).elf_file_name());
+                (name, elf_path)
+            })
+            .collect()
+    }
+
     /// Get the program's root directory path based on the given path.
     ///
     /// The given path can be any path that allows a way to find the program's path, e.g program's
     /// keypair file is named after the program's name and it can be used to get the program's path.
-    async fn get_program_path(&self, path: &Path) -> Option<PathBuf>;
+    async fn get_program_path(&self, path: &Path) -> Option<PathBuf> {
+        self.project_map().get_program_path(path).await
+    }
 
     /// Update the program id.
     ///
+    /// Returns the new program id if it was updated.
+    ///
     /// Default implementation is for Rust.
-    async fn update_program_id(&self, program_keypair_path: &Path) -> miette::Result<()> {
-        if let Some(program_path) = self.get_program_path(program_keypair_path).await {
-            find_and_update_program_id(program_path, program_keypair_path).await?;
+    async fn update_program_id(
+        &self,
+        program_keypair_path: &Path,
+    ) -> miette::Result<Option<String>> {
+        match self.get_program_path(program_keypair_path).await {
+            Some(program_path) => {
+                find_and_update_program_id(program_path, program_keypair_path, self.project_map())
+                    .await
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Update the program id and emit [`HookEvent::ProgramIdUpdated`] if it was updated.
+    async fn run_update_program_id(&self, program_keypair_path: &Path) -> miette::Result<()> {
+        if let Some(program_id) = self.update_program_id(program_keypair_path).await? {
+            if let Some(program_name) = ProgramName::from_keypair_path(program_keypair_path) {
+                set_program_id(&program_name, &program_id).await;
+            }
+            if let Some(program_path) = self.get_program_path(program_keypair_path).await {
+                self.emit(HookEvent::ProgramIdUpdated {
+                    program_path,
+                    program_id,
+                })
+                .await?;
+            }
         }
 
         Ok(())
     }
 
+    /// Paths to build during the setup, i.e. when `target/deploy` doesn't exist yet.
+    ///
+    /// Default implementation builds the whole project at once.
+    async fn setup_build_paths(&self) -> Vec<PathBuf> {
+        vec![self.origin().to_path_buf()]
+    }
+
     /// Build command to run.
     async fn build(&self, program_path: &Path) -> WCommand;
 
+    /// Run the [`Framework::build`] command and emit the build events.
+    ///
+    /// Returns whether the build was successful.
+    async fn run_build(&self, program_path: &Path, output: CommandOutput) -> miette::Result<bool> {
+        self.emit(HookEvent::BeforeBuild {
+            program_path: program_path.to_path_buf(),
+        })
+        .await?;
+
+        report::snapshot_elf_sizes(self, program_path).await;
+        idl::snapshot_idls(self, program_path).await;
+
+        // Output is parsed for the warnings, still show it if it would be inherited
+        let output = match output {
+            CommandOutput::Inherit => CommandOutput::Tee,
+            output => output,
+        };
+
+        let start = Instant::now();
+        let output = self.build(program_path).await.run(output).await?;
+        let mut success = output.status().success();
+
+        let captured = output.captured().unwrap_or_default();
+        if !success {
+            self.report_build_errors(program_path, &captured).await?;
+        }
+
+        let stack_warnings = parse_stack_warnings(&captured);
+        let deny = !stack_warnings.is_empty() && self.config().build.deny_stack_warnings;
+        for warning in stack_warnings {
+            let message = warning.to_string();
+            print_diagnostic(warning);
+            self.emit(HookEvent::Warning { message }).await?;
+        }
+
+        let elf_paths = self
+            .get_elf_paths(program_path)
+            .await
+            .into_iter()
+            .map(|(_, elf_path)| elf_path);
+        if deny {
+            success = false;
+            set_deploy_blocked(elf_paths, true).await;
+            print_error("Build failed because of the stack offset warnings");
+        } else if success {
+            self.post_build(program_path).await?;
+            report::report_elf_sizes(self, program_path).await?;
+            if idl::process_idls(self, program_path).await? {
+                success = false;
+                set_deploy_blocked(elf_paths, true).await;
+                print_error("Build failed because of the breaking IDL changes");
+            } else {
+                set_deploy_blocked(elf_paths, false).await;
+            }
+        }
+
+        self.emit(HookEvent::AfterBuild {
+            program_path: program_path.to_path_buf(),
+            success,
+            duration: start.elapsed(),
+            output: output.captured(),
+        })
+        .await?;
+
+        Ok(success)
+    }
+
+    /// Report the errors of a failed build from its captured output, e.g. by mapping them back to
+    /// the source files.
+    ///
+    /// Default implementation does nothing.
+    async fn report_build_errors(&self, _program_path: &Path, _output: &str) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Run the framework specific steps after a successful build, e.g. generating the IDL.
+    ///
+    /// Default implementation does nothing.
+    async fn post_build(&self, _program_path: &Path) -> miette::Result<()> {
+        Ok(())
+    }
+
     /// Deploy command to run.
     async fn deploy(&self, elf_path: &Path) -> WCommand;
+
+    /// Run the [`Framework::deploy`] command and emit the deploy events.
+    ///
+    /// Returns whether the deployment was successful.
+    async fn run_deploy(&self, elf_path: &Path, output: CommandOutput) -> miette::Result<bool> {
+        if is_deploy_blocked(elf_path).await {
+            self.warn(format!(
+                "Deployment of `{}` is blocked because of the denied build warnings, fix them and \
+                rebuild",
+                elf_path.display()
+            ))
+            .await?;
+            return Ok(false);
+        }
+
+        self.pre_deploy(elf_path).await?;
+        self.emit(HookEvent::BeforeDeploy {
+            elf_path: elf_path.to_path_buf(),
+        })
+        .await?;
+
+        let start = Instant::now();
+        let output = self.deploy(elf_path).await.run(output).await?;
+        let (program_id, signature) = parse_deploy_output(output.stdout());
+        let success = output.status().success();
+        let mut captured = output.captured();
+        if success {
+            if let Some(program_name) = ProgramName::from_elf_path(elf_path) {
+                report::reset_compute_units(&program_name).await;
+                // Program id is only printed when the output is captured
+                let deployed_program_id = match &program_id {
+                    Some(program_id) => Some(program_id.to_owned()),
+                    None => get_program_id_from_keypair(elf_path).await,
+                };
+                if let Some(program_id) = deployed_program_id {
+                    set_program_id(&program_name, program_id).await;
+                }
+            }
+            self.post_deploy(elf_path).await?;
+        } else {
+            if let Some(captured) = &mut captured {
+                *captured = ErrorDecoder::load(self).await.decode(captured).into_owned();
+            }
+            self.post_failed_deploy(elf_path).await?;
+        }
+
+        self.emit(HookEvent::AfterDeploy {
+            elf_path: elf_path.to_path_buf(),
+            success,
+            duration: start.elapsed(),
+            program_id,
+            signature,
+            output: captured,
+        })
+        .await?;
+
+        Ok(success)
+    }
+
+    /// Check that the program can be deployed before running the [`Framework::deploy`] command,
+    /// e.g. that the generated program exists.
+    ///
+    /// Default implementation does nothing.
+    async fn pre_deploy(&self, _elf_path: &Path) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Run the framework specific steps after a successful deployment, e.g. upgrading the IDL.
+    ///
+    /// Default implementation does nothing.
+    async fn post_deploy(&self, _elf_path: &Path) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Run the framework specific steps once after a batch of deployments, e.g. the initial
+    /// deployment of all programs or the deployments of a single watch action.
+    ///
+    /// `elf_paths` are the ELFs that were deployed successfully, they might be empty.
+    ///
+    /// Default implementation does nothing.
+    async fn post_deploy_batch(&self, _elf_paths: &[PathBuf]) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Run the framework specific steps after a failed deployment, e.g. closing the buffer account.
+    ///
+    /// Default implementation does nothing.
+    async fn post_failed_deploy(&self, _elf_path: &Path) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Close command to run.
+    ///
+    /// The program id is the one that was recorded when the program was deployed or its program
+    /// id was updated. Otherwise, it's read from the program keypair next to the given ELF path,
+    /// which means the keypair must still exist.
+    ///
+    /// Default implementation uses `solana program close`.
+    async fn close(&self, elf_path: &Path) -> miette::Result<WCommand> {
+        let recorded_program_id = match ProgramName::from_elf_path(elf_path) {
+            Some(program_name) => get_program_id(&program_name).await,
+            None => None,
+        };
+        let program_id = match recorded_program_id {
+            Some(program_id) => program_id,
+            None => get_program_id_from_keypair(elf_path)
+                .await
+                .ok_or_else(|| WatchError::CouldNotGetKeypair(elf_path.display().to_string()))?,
+        };
+
+        Ok(WCommand::new(format!(
+            "solana program close {program_id} --bypass-warning"
+        )))
+    }
+
+    /// Run the [`Framework::close`] command.
+    ///
+    /// Failures are reported as warnings because the ELF is already removed.
+    async fn run_close(&self, elf_path: &Path) -> miette::Result<()> {
+        let output = match self.close(elf_path).await {
+            Ok(mut close) => close.run(CommandOutput::Inherit).await,
+            Err(err) => Err(err),
+        };
+        let mut message = format!("Couldn't close the program of `{}`", elf_path.display());
+        match output {
+            Ok(output) if output.status().success() => return Ok(()),
+            Ok(output) => {
+                if let Some(captured) = output.captured() {
+                    message.push('\n');
+                    message.push_str(&captured);
+                }
+            }
+            Err(err) => message.push_str(&format!(": {err}")),
+        }
+
+        self.warn(message).await
+    }
+}
+
+/// Get the program id from the program keypair next to the given ELF path.
+async fn get_program_id_from_keypair(elf_path: &Path) -> Option<String> {
+    let program_keypair_path = ProgramName::from_elf_path(elf_path).and_then(|program_name| {
+        elf_path
+            .parent()
+            .map(|deploy_path| deploy_path.join(program_name.keypair_file_name()))
+    })?;
+    if !program_keypair_path.exists() {
+        return None;
+    }
+
+    get_pubkey_from_keypair_path(program_keypair_path)
+        .await
+        .ok()
 }
@@ -1,22 +1,32 @@
 //! Utilities for framework implementations.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use cargo_toml::{Dependency, Manifest};
 use lazy_static::lazy_static;
 use miette::IntoDiagnostic;
 use regex::{Match, Regex, RegexBuilder};
-use tokio::{fs, sync::RwLock, time};
+use tokio::{
+    fs,
+    process::Child,
+    sync::{Mutex, RwLock},
+    time,
+};
 use watchexec_filterer_globset::GlobsetFilterer;
 
 use crate::{
     command::WCommand,
+    config::Config,
     constants::{dirname, extension, filename},
     error::WatchError,
+    fixtures,
     glob::glob,
+    idl,
+    output::print_warning,
     toml::read_cargo_toml,
 };
 
@@ -62,8 +72,43 @@ impl ProjectMap {
         program_hm.insert(name.into(), path.into());
     }
 
+    /// Replace all the programs with the given mapping, removing the stale programs.
+    pub async fn replace(&self, program_hm: HashMap<String, PathBuf>) {
+        *self.0.write().await = program_hm;
+    }
+
+    /// Get all the program names and their paths.
+    pub async fn get_all(&self) -> HashMap<String, PathBuf> {
+        self.0.read().await.clone()
+    }
+
+    /// Get the path of the program that contains the given file path.
+    ///
+    /// Unlike [`get_program_path`], this doesn't require the file or its directory to exist.
+    pub async fn get_program_path_from_file_path<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+    ) -> Option<PathBuf> {
+        self.0
+            .read()
+            .await
+            .values()
+            .filter(|program_path| file_path.as_ref().starts_with(program_path))
+            .max_by_key(|program_path| program_path.components().count())
+            .map(|program_path| program_path.to_owned())
+    }
+
+    /// Returns whether the given path is the root directory of a mapped program.
+    pub async fn contains_program_path<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.0
+            .read()
+            .await
+            .values()
+            .any(|program_path| program_path == path.as_ref())
+    }
+
     /// Get the program path from the program name.
-    async fn get_program_path_from_name<S: AsRef<str>>(&self, name: S) -> Option<PathBuf> {
+    pub async fn get_program_path_from_name<S: AsRef<str>>(&self, name: S) -> Option<PathBuf> {
         self.0
             .read()
             .await
@@ -116,6 +161,21 @@ impl ProgramName {
             .map(|name| Self::new(name.trim_end_matches(suffix.as_ref())))
     }
 
+    /// Get the program keypair file name, i.e. `<program_name>-keypair.json`.
+    pub fn keypair_file_name(&self) -> String {
+        format!("{}-keypair.json", self.snake_case())
+    }
+
+    /// Get the program ELF file name, i.e. `<program_name>.so`.
+    pub fn elf_file_name(&self) -> String {
+        format!("{}.{}", self.snake_case(), extension::SO)
+    }
+
+    /// Get the program IDL file name, i.e. `<program_name>.json`.
+    pub fn idl_file_name(&self) -> String {
+        format!("{}.{}", self.snake_case(), extension::JSON)
+    }
+
     /// Reference to the original program name.
     pub fn original(&self) -> &str {
         &self.0
@@ -125,23 +185,82 @@ impl ProgramName {
     pub fn kebab_case(&self) -> String {
         self.0.replace('_', "-")
     }
+
+    /// Convert the original program name to snake_case.
+    pub fn snake_case(&self) -> String {
+        self.0.replace('-', "_")
+    }
+}
+
+lazy_static! {
+    /// Test validator process that was started by this process.
+    static ref TEST_VALIDATOR: Mutex<Option<Child>> = Mutex::new(None);
+    /// ELFs that can't be deployed because their build had denied warnings.
+    static ref BLOCKED_ELFS: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+    /// Program ids by the program names, recorded at deployment and program id update time.
+    static ref PROGRAM_IDS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
 }
 
 /// Start a new test validator by running `solana-test-validator` command.
 ///
 /// This won't have any effect if there is already a running test validator.
 ///
-/// NOTE: This function will spawn a tokio task because `solana-test-validator` command never
-/// resolves. It will then sleep for a small duration to give time for the initialization. This
-/// means it will not confirm that the test validator has started.
-pub async fn start_test_validator<P: Into<PathBuf>>(origin: P) -> miette::Result<()> {
-    let origin = origin.into();
-    tokio::spawn(async {
-        let _ = WCommand::new("solana-test-validator")
-            .current_dir(origin)
-            .output()
-            .await;
-    });
+/// NOTE: This function will spawn the `solana-test-validator` command in the background because it
+/// never resolves. It will then sleep for a small duration to give time for the initialization.
+/// This means it will not confirm that the test validator has started.
+pub async fn start_test_validator<P: AsRef<Path>>(
+    origin: P,
+    config: &Config,
+) -> miette::Result<()> {
+    spawn_test_validator(origin, config, false).await
+}
+
+/// Restart the test validator with `--reset` flag, which means all the accounts and programs are
+/// removed from the ledger.
+///
+/// Returns an error if the running test validator was not started by this process.
+pub async fn restart_test_validator<P: AsRef<Path>>(
+    origin: P,
+    config: &Config,
+) -> miette::Result<()> {
+    if let Some(mut child) = TEST_VALIDATOR.lock().await.take() {
+        if matches!(child.try_wait(), Ok(None)) {
+            child.kill().await.into_diagnostic()?;
+            idl::reset().await;
+            fixtures::reset_seeded();
+            return spawn_test_validator(origin, config, true).await;
+        }
+    }
+
+    Err(WatchError::TestValidatorNotOwned)?
+}
+
+/// Spawn `solana-test-validator` command in the background and wait for it to initialize.
+///
+/// Account fixtures are loaded into the ledger with `--account`, which `solana-test-validator`
+/// ignores when the ledger already exists.
+async fn spawn_test_validator<P: AsRef<Path>>(
+    origin: P,
+    config: &Config,
+    reset: bool,
+) -> miette::Result<()> {
+    let cmd = match reset {
+        true => "solana-test-validator --reset",
+        false => "solana-test-validator",
+    };
+    let mut command = WCommand::new(cmd);
+    let fixture_args = fixtures::get_validator_args(&origin, &config.fixtures).await;
+    if !reset && !fixture_args.is_empty() && origin.as_ref().join(dirname::TEST_LEDGER).is_dir() {
+        print_warning(
+            "Account fixtures are only loaded into a new ledger, reset the test validator to \
+            load them",
+        );
+    }
+    for arg in fixture_args {
+        command.arg(arg);
+    }
+    let child = command.current_dir(origin).spawn_background()?;
+    *TEST_VALIDATOR.lock().await = Some(child);
 
     // Wait 2 seconds for the test validator to start
     time::sleep(time::Duration::from_secs(2)).await;
@@ -149,6 +268,42 @@ pub async fn start_test_validator<P: Into<PathBuf>>(origin: P) -> miette::Result
     Ok(())
 }
 
+/// Block or unblock the deployment of the given ELFs.
+pub async fn set_deploy_blocked<I: IntoIterator<Item = PathBuf>>(elf_paths: I, blocked: bool) {
+    let mut blocked_elfs = BLOCKED_ELFS.lock().await;
+    for elf_path in elf_paths {
+        match blocked {
+            true => blocked_elfs.insert(elf_path),
+            false => blocked_elfs.remove(&elf_path),
+        };
+    }
+}
+
+/// Returns whether the deployment of the ELF is blocked.
+pub async fn is_deploy_blocked<P: AsRef<Path>>(elf_path: P) -> bool {
+    BLOCKED_ELFS.lock().await.contains(elf_path.as_ref())
+}
+
+/// Record the program id of the program.
+pub async fn set_program_id<S: Into<String>>(program_name: &ProgramName, program_id: S) {
+    PROGRAM_IDS
+        .lock()
+        .await
+        .insert(program_name.original().to_owned(), program_id.into());
+}
+
+/// Get the recorded program id of the program.
+///
+/// Unlike the program keypair, the recorded program id is still available after the program
+/// files are removed, e.g. with `cargo clean`.
+pub async fn get_program_id(program_name: &ProgramName) -> Option<String> {
+    PROGRAM_IDS
+        .lock()
+        .await
+        .get(program_name.original())
+        .cloned()
+}
+
 /// Get all the directory paths that will be watched by default.
 ///
 /// If the `origin` is a workspace, the paths will be filtered by `workspace.members` and
@@ -182,15 +337,26 @@ async fn filter_workspace_programs<P: AsRef<Path>>(
 }
 
 /// Get a mapping of program names and paths based on the manifest file at `origin`.
-pub async fn get_program_name_path_hashmap<P: AsRef<Path>>(
+///
+/// Only the crates whose manifests match the `is_program` predicate are included, e.g. to skip
+/// the library crates of the workspace.
+pub async fn get_program_name_path_hashmap<P, F>(
     origin: P,
-) -> miette::Result<HashMap<String, PathBuf>> {
+    is_program: F,
+) -> miette::Result<HashMap<String, PathBuf>>
+where
+    P: AsRef<Path>,
+    F: Fn(&Manifest) -> bool,
+{
     let mut program_name_path_hm = HashMap::new();
     let program_paths = filter_workspace_programs(&origin)
         .await?
         .unwrap_or(vec![origin.as_ref().to_path_buf()]);
     for program_path in program_paths {
         if let Ok(manifest) = read_cargo_toml(&program_path).await {
+            if !is_program(&manifest) {
+                continue;
+            }
             if let Some(package) = manifest.package {
                 program_name_path_hm.insert(package.name, program_path);
             }
@@ -200,6 +366,15 @@ pub async fn get_program_name_path_hashmap<P: AsRef<Path>>(
     Ok(program_name_path_hm)
 }
 
+/// Returns whether the crate has a `cdylib` target, which means it's a program rather than a
+/// library.
+pub fn is_cdylib(manifest: &Manifest) -> bool {
+    manifest
+        .lib
+        .as_ref()
+        .is_some_and(|lib| lib.crate_type.iter().any(|ty| ty == "cdylib"))
+}
+
 /// Get program's root path by running `cargo locate-project` command.
 pub async fn get_program_path<P: AsRef<Path>>(modified_file_path: P) -> miette::Result<PathBuf> {
     let output = WCommand::new("cargo locate-project --message-format plain")
@@ -238,14 +413,45 @@ pub async fn get_pubkey_from_keypair_path<P: AsRef<Path>>(
     Ok(program_id.to_owned())
 }
 
-/// Find the file that includes `declare_id!` macro and update the program id if it has changed.
+/// Parse the program id and the transaction signature from the deploy command's stdout.
+///
+/// Both `solana program deploy` and `anchor deploy` print the program id as `Program Id: <id>`
+/// and newer Solana CLI versions also print the signature as `Signature: <signature>`.
+pub fn parse_deploy_output<S: AsRef<str>>(stdout: S) -> (Option<String>, Option<String>) {
+    lazy_static! {
+        static ref PROGRAM_ID_REGEX: Regex = RegexBuilder::new(r"^Program Id: (\w+)")
+            .multi_line(true)
+            .build()
+            .unwrap();
+        static ref SIGNATURE_REGEX: Regex = RegexBuilder::new(r"^Signature: (\w+)")
+            .multi_line(true)
+            .build()
+            .unwrap();
+    };
+
+    let capture = |regex: &Regex| {
+        regex
+            .captures(stdout.as_ref())
+            .and_then(|captures| captures.get(1))
+            .map(|m| m.as_str().to_owned())
+    };
+
+    (capture(&PROGRAM_ID_REGEX), capture(&SIGNATURE_REGEX))
+}
+
+/// Find the file that declares the program id and update the program id if it has changed.
 ///
 /// This function will check `lib.rs` first and **only** if it doesn't find the declaration it will
-/// then check all the remaining source files.
+/// then check all the remaining source files, and the source files of the local path
+/// dependencies, e.g. the `api` crate of Steel programs. Path dependencies that are programs
+/// themselves, e.g. the other programs of the workspace that are used for CPI, are skipped.
+///
+/// Returns the new program id if it was updated.
 pub async fn find_and_update_program_id<P1, P2>(
     program_path: P1,
     program_keypair_path: P2,
-) -> miette::Result<()>
+    project_map: &ProjectMap,
+) -> miette::Result<Option<String>>
 where
     P1: AsRef<Path>,
     P2: AsRef<Path>,
@@ -253,74 +459,175 @@ where
     // Get the keypair program id
     let program_id = get_pubkey_from_keypair_path(program_keypair_path).await?;
 
-    // Check lib.rs first for the program id
-    let src_path = program_path.as_ref().join(dirname::SRC);
-    let lib_rs_path = src_path.join(filename::LIB_RS);
+    match update_program_id_declaration(program_path, &program_id, project_map).await? {
+        true => Ok(Some(program_id)),
+        false => Ok(None),
+    }
+}
+
+/// Update the first program id declaration of the program's crates with the given program id.
+///
+/// The search stops at the first declaration even if it's already up to date, the declarations
+/// after it are usually the ids of other programs, e.g. `src/spl_noop.rs`.
+///
+/// Returns whether the program id was updated.
+async fn update_program_id_declaration<P: AsRef<Path>>(
+    program_path: P,
+    program_id: &str,
+    project_map: &ProjectMap,
+) -> miette::Result<bool> {
+    let mut crate_paths = vec![program_path.as_ref().to_path_buf()];
+    for path in get_path_dependency_paths(program_path).await {
+        if project_map.contains_program_path(&path).await {
+            continue;
+        }
+        if read_cargo_toml(&path)
+            .await
+            .map_or(true, |manifest| is_cdylib(&manifest))
+        {
+            continue;
+        }
+
+        crate_paths.push(path);
+    }
+    for crate_path in crate_paths {
+        // Check lib.rs first for the program id
+        let src_path = crate_path.join(dirname::SRC);
+        let lib_rs_path = src_path.join(filename::LIB_RS);
+        if lib_rs_path.exists() {
+            if let Some(updated) = update_rust_program_id(&lib_rs_path, program_id).await? {
+                return Ok(updated);
+            }
+        }
 
-    if update_rust_program_id(lib_rs_path, &program_id).await? {
-        return Ok(());
+        // Check all the other files if the program_id doesn't exist in lib.rs
+        let rust_src_paths = glob(src_path, [format!("*.{}", extension::RS)], [], false).await?;
+        for path in rust_src_paths
+            .into_iter()
+            .filter(|path| *path != lib_rs_path)
+        {
+            if let Some(updated) = update_rust_program_id(path, program_id).await? {
+                // Not necessary to continue the loop after finding the declaration
+                return Ok(updated);
+            }
+        }
     }
 
-    // Check all the other files if the program_id doesn't exist in lib.rs
-    let rust_src_paths = glob(src_path, [format!("*.{}", extension::RS)], [], false).await?;
-    for path in rust_src_paths {
-        if update_rust_program_id(path, &program_id).await? {
-            // Not necessary to continue the loop after program id update
+    Ok(false)
+}
+
+/// Get the paths of the local path dependencies of the program, including the path dependencies
+/// that are inherited from the workspace.
+///
+/// Paths are canonicalized to be comparable with the program paths, e.g. `program/../api`.
+async fn get_path_dependency_paths<P: AsRef<Path>>(program_path: P) -> Vec<PathBuf> {
+    let program_path = program_path.as_ref();
+    let Ok(manifest) = read_cargo_toml(program_path).await else {
+        return vec![];
+    };
+
+    // Closest ancestor with a workspace manifest
+    let mut workspace = None;
+    for path in program_path.ancestors().skip(1) {
+        if let Ok(Manifest {
+            workspace: Some(manifest_workspace),
+            ..
+        }) = read_cargo_toml(path).await
+        {
+            workspace = Some((path.to_path_buf(), manifest_workspace));
             break;
         }
     }
 
-    Ok(())
+    let paths = manifest
+        .dependencies
+        .iter()
+        .filter_map(|(name, dependency)| match dependency {
+            Dependency::Detailed(detail) => Some(program_path.join(detail.path.as_ref()?)),
+            Dependency::Inherited(_) => {
+                let (workspace_path, workspace) = workspace.as_ref()?;
+                let path = workspace.dependencies.get(name)?.detail()?.path.as_ref()?;
+                Some(workspace_path.join(path))
+            }
+            Dependency::Simple(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut canonical_paths = vec![];
+    for path in paths {
+        canonical_paths.push(fs::canonicalize(&path).await.unwrap_or(path));
+    }
+
+    canonical_paths
 }
 
-/// Update the file at the given path's `declare_id!` macro with the given program id.
+/// Update the program id declaration of the file at the given path with the given program id.
 ///
-/// Returns whether the program id was updated successfully.
-async fn update_rust_program_id<P, S>(path: P, program_id: S) -> miette::Result<bool>
+/// Returns `None` if the file doesn't declare a program id, otherwise whether the program id was
+/// updated.
+async fn update_rust_program_id<P, S>(path: P, program_id: S) -> miette::Result<Option<bool>>
 where
     P: AsRef<Path>,
     S: AsRef<str>,
 {
+    update_file_program_id_with(path, &program_id, find_rust_program_id).await
+}
+
+/// Find the program id of the program id declaration in the Rust source.
+///
+/// Top-level `declare_id!` macros with any path prefix, e.g. `pinocchio_pubkey::declare_id!`, are
+/// checked first. Otherwise, top-level `ID` or `PROGRAM_ID` constants that are initialized from a
+/// string literal are used, e.g. `pub const ID: Pubkey = pubkey!("...")`. Indented declarations
+/// are skipped because they are usually the ids of other programs inside modules.
+fn find_rust_program_id(content: &str) -> Option<Match<'_>> {
     lazy_static! {
-        static ref REGEX: Regex = RegexBuilder::new(r#"^(([\w]+::)*)declare_id!\("(\w*)"\)"#)
-            .multi_line(true)
-            .build()
-            .unwrap();
+        static ref DECLARE_ID_REGEX: Regex =
+            RegexBuilder::new(r#"^(?:\w+::)*declare_id!\(\s*"(\w*)""#)
+                .multi_line(true)
+                .build()
+                .unwrap();
+        static ref CONST_ID_REGEX: Regex = RegexBuilder::new(
+            r#"^(?:pub(?:\([\w:]+\))?\s+)?const\s+(?:PROGRAM_)?ID\s*:[^=\n]+=[^";\n]*"(\w*)""#
+        )
+        .multi_line(true)
+        .build()
+        .unwrap();
     };
 
-    update_file_program_id_with(path, &program_id, |content| {
-        REGEX.captures(content).and_then(|captures| captures.get(3))
-    })
-    .await
+    [&*DECLARE_ID_REGEX, &*CONST_ID_REGEX]
+        .into_iter()
+        .find_map(|regex| regex.captures(content).and_then(|captures| captures.get(1)))
 }
 
 /// Update the file's `declare_id!` macro with the program id based on the given callback.
 ///
-/// Returns whether the program id was updated successfully.
+/// Returns `None` if the callback doesn't find the declaration, otherwise whether the program id
+/// was updated.
 pub async fn update_file_program_id_with<P, S, F>(
     path: P,
     program_id: S,
     cb: F,
-) -> miette::Result<bool>
+) -> miette::Result<Option<bool>>
 where
     P: AsRef<Path>,
     S: AsRef<str>,
     F: Fn(&str) -> Option<Match<'_>>,
 {
     let mut content = fs::read_to_string(&path).await.into_diagnostic()?;
-    if let Some(program_id_match) =
-        cb(&content).filter(|program_id_match| program_id_match.as_str() != program_id.as_ref())
-    {
-        // Update the program id
-        content.replace_range(program_id_match.range(), program_id.as_ref());
+    let Some(program_id_match) = cb(&content) else {
+        return Ok(None);
+    };
+    if program_id_match.as_str() == program_id.as_ref() {
+        return Ok(Some(false));
+    }
 
-        // Save the file
-        fs::write(&path, content).await.into_diagnostic()?;
+    // Update the program id
+    content.replace_range(program_id_match.range(), program_id.as_ref());
 
-        return Ok(true);
-    }
+    // Save the file
+    fs::write(&path, content).await.into_diagnostic()?;
 
-    Ok(false)
+    Ok(Some(true))
 }
 
 /// Get Solana build tool.
@@ -377,3 +684,113 @@ pub async fn create_globset_filterer<P: AsRef<Path>>(
             .unwrap(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_rust_program_id_prefers_declare_id() {
+        let content = r#"use pinocchio::pubkey::Pubkey;
+
+pub mod token {
+    pub const ID: Pubkey = five8_const::decode_32_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+pinocchio_pubkey::declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+"#;
+
+        let program_id = find_rust_program_id(content).unwrap();
+        assert_eq!(
+            program_id.as_str(),
+            "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+        );
+    }
+
+    #[test]
+    fn find_rust_program_id_top_level_const() {
+        let content = r#"use steel::*;
+
+mod token {
+    pub const ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+pub const PROGRAM_ID: Pubkey = pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+"#;
+
+        let program_id = find_rust_program_id(content).unwrap();
+        assert_eq!(
+            program_id.as_str(),
+            "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+        );
+    }
+
+    #[test]
+    fn find_rust_program_id_skips_nested_consts() {
+        let content = r#"pub mod token {
+    pub const ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+"#;
+
+        assert!(find_rust_program_id(content).is_none());
+    }
+
+    #[test]
+    fn find_rust_program_id_skips_nested_declare_id() {
+        let content = r#"use anchor_lang::prelude::*;
+
+pub mod metadata {
+    solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+"#;
+
+        let program_id = find_rust_program_id(content).unwrap();
+        assert_eq!(
+            program_id.as_str(),
+            "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+        );
+    }
+
+    #[tokio::test]
+    async fn update_program_id_declaration_stops_at_first_declaration() {
+        const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+        const NOOP_PROGRAM_ID: &str = "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV";
+
+        let program_path =
+            std::env::temp_dir().join(format!("watchso-program-id-{}", std::process::id()));
+        let src_path = program_path.join(dirname::SRC);
+        let lib_rs_path = src_path.join(filename::LIB_RS);
+        let noop_rs_path = src_path.join("spl_noop.rs");
+        fs::create_dir_all(&src_path).await.unwrap();
+        fs::write(&lib_rs_path, format!("declare_id!(\"{PROGRAM_ID}\");\n"))
+            .await
+            .unwrap();
+        fs::write(
+            &noop_rs_path,
+            format!("declare_id!(\"{NOOP_PROGRAM_ID}\");\n"),
+        )
+        .await
+        .unwrap();
+
+        let project_map = ProjectMap::default();
+        let unchanged = update_program_id_declaration(&program_path, PROGRAM_ID, &project_map)
+            .await
+            .unwrap();
+        let noop_rs = fs::read_to_string(&noop_rs_path).await.unwrap();
+
+        // Update the out of date declaration of `lib.rs`
+        let new_program_id = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+        let updated = update_program_id_declaration(&program_path, new_program_id, &project_map)
+            .await
+            .unwrap();
+        let lib_rs = fs::read_to_string(&lib_rs_path).await.unwrap();
+        fs::remove_dir_all(&program_path).await.unwrap();
+
+        assert!(!unchanged);
+        assert!(noop_rs.contains(NOOP_PROGRAM_ID));
+        assert!(updated);
+        assert!(lib_rs.contains(new_program_id));
+    }
+}
@@ -0,0 +1,385 @@
+//! Hooks that run on watch events.
+//!
+//! Hooks can either be Rust closures subscribed with [`Hooks::subscribe`] or shell commands
+//! configured in the `[hooks]` table of the config file.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Serialize, Serializer};
+use tokio::sync::RwLock;
+
+use crate::{
+    command::{CommandOutput, WCommand},
+    config::HooksConfig,
+    error::WatchError,
+    output::print_error,
+};
+
+/// Rust closure hook.
+///
+/// Returning an error from a `before_*` hook cancels the operation.
+pub type HookFn = Box<dyn Fn(&HookEvent) -> miette::Result<()> + Send + Sync>;
+
+/// Kind of the [`HookEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HookKind {
+    /// Watched files have changed.
+    ChangeDetected,
+    /// Program build is about to start.
+    BeforeBuild,
+    /// Program build has finished.
+    AfterBuild,
+    /// Program deployment is about to start.
+    BeforeDeploy,
+    /// Program deployment has finished.
+    AfterDeploy,
+    /// Program id in the source code has been updated.
+    ProgramIdUpdated,
+    /// Program has logged a transaction.
+    ProgramLog,
+    /// IDL of a program has changed after a build.
+    IdlChanged,
+    /// ELF size of a program has been measured after a build.
+    ElfSize,
+    /// Consumed compute units of an instruction have been measured from the program logs.
+    ComputeUnits,
+    /// A warning has occurred.
+    Warning,
+    /// An error has occurred.
+    Error,
+}
+
+impl HookKind {
+    /// Name of the hook, same as its key in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ChangeDetected => "on_change_detected",
+            Self::BeforeBuild => "before_build",
+            Self::AfterBuild => "after_build",
+            Self::BeforeDeploy => "before_deploy",
+            Self::AfterDeploy => "after_deploy",
+            Self::ProgramIdUpdated => "on_program_id_updated",
+            Self::ProgramLog => "on_program_log",
+            Self::IdlChanged => "on_idl_changed",
+            Self::ElfSize => "on_elf_size",
+            Self::ComputeUnits => "on_compute_units",
+            Self::Warning => "on_warning",
+            Self::Error => "on_error",
+        }
+    }
+
+    /// Returns whether a failing hook of this kind cancels the operation, i.e. `before_build` and
+    /// `before_deploy`.
+    pub fn is_cancellable(&self) -> bool {
+        matches!(self, Self::BeforeBuild | Self::BeforeDeploy)
+    }
+}
+
+/// Watch event that is passed to the hooks.
+///
+/// Serializes to a JSON object with the event name in the `event` field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// Watched files have changed.
+    ChangeDetected {
+        /// Changed paths
+        paths: Vec<PathBuf>,
+    },
+    /// Program build is about to start.
+    BeforeBuild {
+        /// Root directory of the program
+        program_path: PathBuf,
+    },
+    /// Program build has finished.
+    AfterBuild {
+        /// Root directory of the program
+        program_path: PathBuf,
+        /// Whether the build was successful
+        success: bool,
+        /// Duration of the build
+        #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+        duration: Duration,
+        /// Build output, only available if the command output was captured
+        output: Option<String>,
+    },
+    /// Program deployment is about to start.
+    BeforeDeploy {
+        /// Path of the program ELF
+        elf_path: PathBuf,
+    },
+    /// Program deployment has finished.
+    AfterDeploy {
+        /// Path of the program ELF
+        elf_path: PathBuf,
+        /// Whether the deployment was successful
+        success: bool,
+        /// Duration of the deployment
+        #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+        duration: Duration,
+        /// Deployed program id, only available if the command output was captured
+        program_id: Option<String>,
+        /// Deployment transaction signature, only available if the command output was captured
+        signature: Option<String>,
+        /// Deployment output, only available if the command output was captured
+        output: Option<String>,
+    },
+    /// Program id in the source code has been updated.
+    ProgramIdUpdated {
+        /// Root directory of the program
+        program_path: PathBuf,
+        /// New program id
+        program_id: String,
+    },
+    /// Program has logged a transaction.
+    ProgramLog {
+        /// Program name
+        program: String,
+        /// Program id
+        program_id: String,
+        /// Transaction signature
+        signature: String,
+        /// Whether the transaction was successful
+        success: bool,
+        /// Log messages of the transaction
+        logs: Vec<String>,
+    },
+    /// IDL of a program has changed after a build.
+    IdlChanged {
+        /// Program name
+        program: String,
+        /// Path of the program IDL
+        idl_path: PathBuf,
+    },
+    /// ELF size of a program has been measured after a build.
+    ElfSize {
+        /// Program name
+        program: String,
+        /// Path of the program ELF
+        elf_path: PathBuf,
+        /// Size of the ELF in bytes
+        size: u64,
+        /// Size of the ELF before the build, not available if the ELF didn't exist
+        previous_size: Option<u64>,
+    },
+    /// Consumed compute units of an instruction have been measured from the program logs.
+    ComputeUnits {
+        /// Program name
+        program: String,
+        /// Instruction name, from the `Instruction: <name>` log of Anchor programs
+        instruction: String,
+        /// Consumed compute units
+        consumed: u64,
+        /// Consumed compute units before the last deployment, not available if the instruction
+        /// was not called
+        previous: Option<u64>,
+    },
+    /// A warning has occurred.
+    Warning {
+        /// Warning message
+        message: String,
+    },
+    /// An error has occurred.
+    Error {
+        /// Error message
+        message: String,
+    },
+}
+
+impl HookEvent {
+    /// Get the kind of the event.
+    pub fn kind(&self) -> HookKind {
+        match self {
+            Self::ChangeDetected { .. } => HookKind::ChangeDetected,
+            Self::BeforeBuild { .. } => HookKind::BeforeBuild,
+            Self::AfterBuild { .. } => HookKind::AfterBuild,
+            Self::BeforeDeploy { .. } => HookKind::BeforeDeploy,
+            Self::AfterDeploy { .. } => HookKind::AfterDeploy,
+            Self::ProgramIdUpdated { .. } => HookKind::ProgramIdUpdated,
+            Self::ProgramLog { .. } => HookKind::ProgramLog,
+            Self::IdlChanged { .. } => HookKind::IdlChanged,
+            Self::ElfSize { .. } => HookKind::ElfSize,
+            Self::ComputeUnits { .. } => HookKind::ComputeUnits,
+            Self::Warning { .. } => HookKind::Warning,
+            Self::Error { .. } => HookKind::Error,
+        }
+    }
+
+    /// Get the environment variables that are passed to the shell command hooks.
+    fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let path_var = |path: &Path| path.display().to_string();
+        let mut env_vars = vec![("WATCHSO_EVENT", self.kind().name().to_owned())];
+        match self {
+            Self::ChangeDetected { paths } => {
+                let paths = paths.iter().map(|path| path_var(path)).collect::<Vec<_>>();
+                env_vars.push(("WATCHSO_PATHS", paths.join("\n")));
+            }
+            Self::BeforeBuild { program_path } => {
+                env_vars.push(("WATCHSO_PROGRAM_PATH", path_var(program_path)));
+            }
+            Self::AfterBuild {
+                program_path,
+                success,
+                duration,
+                ..
+            } => {
+                env_vars.push(("WATCHSO_PROGRAM_PATH", path_var(program_path)));
+                env_vars.push(("WATCHSO_SUCCESS", success.to_string()));
+                env_vars.push(("WATCHSO_DURATION_MS", duration.as_millis().to_string()));
+            }
+            Self::BeforeDeploy { elf_path } => {
+                env_vars.push(("WATCHSO_ELF_PATH", path_var(elf_path)));
+            }
+            Self::AfterDeploy {
+                elf_path,
+                success,
+                duration,
+                program_id,
+                signature,
+                ..
+            } => {
+                env_vars.push(("WATCHSO_ELF_PATH", path_var(elf_path)));
+                env_vars.push(("WATCHSO_SUCCESS", success.to_string()));
+                env_vars.push(("WATCHSO_DURATION_MS", duration.as_millis().to_string()));
+                if let Some(program_id) = program_id {
+                    env_vars.push(("WATCHSO_PROGRAM_ID", program_id.to_owned()));
+                }
+                if let Some(signature) = signature {
+                    env_vars.push(("WATCHSO_SIGNATURE", signature.to_owned()));
+                }
+            }
+            Self::ProgramIdUpdated {
+                program_path,
+                program_id,
+            } => {
+                env_vars.push(("WATCHSO_PROGRAM_PATH", path_var(program_path)));
+                env_vars.push(("WATCHSO_PROGRAM_ID", program_id.to_owned()));
+            }
+            Self::ProgramLog {
+                program,
+                program_id,
+                signature,
+                success,
+                logs,
+            } => {
+                env_vars.push(("WATCHSO_PROGRAM", program.to_owned()));
+                env_vars.push(("WATCHSO_PROGRAM_ID", program_id.to_owned()));
+                env_vars.push(("WATCHSO_SIGNATURE", signature.to_owned()));
+                env_vars.push(("WATCHSO_SUCCESS", success.to_string()));
+                env_vars.push(("WATCHSO_LOGS", logs.join("\n")));
+            }
+            Self::IdlChanged { program, idl_path } => {
+                env_vars.push(("WATCHSO_PROGRAM", program.to_owned()));
+                env_vars.push(("WATCHSO_IDL_PATH", path_var(idl_path)));
+            }
+            Self::ElfSize {
+                program,
+                elf_path,
+                size,
+                previous_size,
+            } => {
+                env_vars.push(("WATCHSO_PROGRAM", program.to_owned()));
+                env_vars.push(("WATCHSO_ELF_PATH", path_var(elf_path)));
+                env_vars.push(("WATCHSO_SIZE", size.to_string()));
+                if let Some(previous_size) = previous_size {
+                    env_vars.push(("WATCHSO_PREVIOUS_SIZE", previous_size.to_string()));
+                }
+            }
+            Self::ComputeUnits {
+                program,
+                instruction,
+                consumed,
+                previous,
+            } => {
+                env_vars.push(("WATCHSO_PROGRAM", program.to_owned()));
+                env_vars.push(("WATCHSO_INSTRUCTION", instruction.to_owned()));
+                env_vars.push(("WATCHSO_CONSUMED", consumed.to_string()));
+                if let Some(previous) = previous {
+                    env_vars.push(("WATCHSO_PREVIOUS_CONSUMED", previous.to_string()));
+                }
+            }
+            Self::Warning { message } => env_vars.push(("WATCHSO_WARNING", message.to_owned())),
+            Self::Error { message } => env_vars.push(("WATCHSO_ERROR", message.to_owned())),
+        }
+
+        env_vars
+    }
+}
+
+/// Registry of the hooks. Using `RwLock` because the process is read heavy.
+#[derive(Default)]
+pub struct Hooks(RwLock<Vec<(Option<HookKind>, HookFn)>>);
+
+impl Hooks {
+    /// Subscribe to the events of the given kind.
+    pub async fn subscribe<F>(&self, kind: HookKind, hook: F)
+    where
+        F: Fn(&HookEvent) -> miette::Result<()> + Send + Sync + 'static,
+    {
+        self.0.write().await.push((Some(kind), Box::new(hook)));
+    }
+
+    /// Subscribe to all events.
+    pub async fn subscribe_all<F>(&self, hook: F)
+    where
+        F: Fn(&HookEvent) -> miette::Result<()> + Send + Sync + 'static,
+    {
+        self.0.write().await.push((None, Box::new(hook)));
+    }
+
+    /// Run all the subscribed closures and the configured shell command of the event.
+    ///
+    /// Shell commands run in the given `origin` directory.
+    ///
+    /// Returns the first error that occurred only if the event is [cancellable], other hook errors
+    /// are printed and ignored. Remaining hooks still run after an error.
+    ///
+    /// [cancellable]: HookKind::is_cancellable
+    pub async fn emit<P: AsRef<Path>>(
+        &self,
+        event: &HookEvent,
+        config: &HooksConfig,
+        origin: P,
+    ) -> miette::Result<()> {
+        let mut result = Ok(());
+        for (kind, hook) in self.0.read().await.iter() {
+            if kind.map(|kind| kind == event.kind()).unwrap_or(true) {
+                if let Err(err) = hook(event) {
+                    result = result.and(Err(err));
+                }
+            }
+        }
+
+        if let Some(cmd) = config.get(event.kind()) {
+            let mut command = WCommand::shell(cmd);
+            command.current_dir(origin);
+            for (key, value) in event.env_vars() {
+                command.env(key, value);
+            }
+
+            match command.run(CommandOutput::Inherit).await {
+                Ok(output) if output.status().success() => (),
+                Ok(_) => {
+                    result = result.and(Err(WatchError::HookFailed(event.kind().name()).into()))
+                }
+                Err(err) => result = result.and(Err(err)),
+            }
+        }
+
+        match result {
+            Err(err) if !event.kind().is_cancellable() => {
+                print_error(err);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+/// Serialize the duration as milliseconds.
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
@@ -0,0 +1,463 @@
+//! Program IDLs in `target/idl`.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use miette::{Diagnostic, IntoDiagnostic, Severity};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    command::WCommand,
+    constants::{dirname, extension},
+    error::WatchError,
+    framework::Framework,
+    framework_utils::ProgramName,
+    hook::HookEvent,
+    output::{print_diagnostic, print_info},
+    progress::Progress,
+};
+
+lazy_static! {
+    /// Last known IDLs by the IDL paths.
+    static ref IDLS: Mutex<HashMap<PathBuf, String>> = Mutex::new(HashMap::new());
+    /// IDLs that were uploaded on-chain by the program ids.
+    static ref UPLOADED_IDLS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    /// Content hashes of the IDLs that the clients were generated from by the IDL paths.
+    static ref GENERATED_IDLS: Mutex<HashMap<PathBuf, u64>> = Mutex::new(HashMap::new());
+}
+
+/// Remember the existing IDLs of the programs inside the given directory.
+///
+/// This should be called before the build to compare the IDLs with the ones of the previous
+/// session.
+pub async fn snapshot_idls<F: Framework + ?Sized>(framework: &F, program_path: &Path) {
+    let mut idls = IDLS.lock().await;
+    for (name, _) in framework.get_elf_paths(program_path).await {
+        let idl_path = get_idl_path(framework, &name);
+        if idls.contains_key(&idl_path) {
+            continue;
+        }
+        if let Ok(idl) = fs::read_to_string(&idl_path).await {
+            idls.insert(idl_path, idl);
+        }
+    }
+}
+
+/// Change of the IDL that breaks the existing accounts or the clients of the program.
+#[derive(Error, Debug)]
+#[error("Breaking IDL change in {program}: {change}")]
+pub struct BreakingChange {
+    /// Program name
+    pub program: String,
+    /// Description of the change
+    pub change: String,
+    /// Whether the breaking changes are denied
+    pub denied: bool,
+}
+
+impl Diagnostic for BreakingChange {
+    fn severity(&self) -> Option<Severity> {
+        match self.denied {
+            true => Some(Severity::Error),
+            false => Some(Severity::Warning),
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(
+            "Existing accounts on the validator may fail to deserialize with the new layout. \
+            Restart the test validator with a reset and rebuild if the change is intended.",
+        ))
+    }
+}
+
+/// Emit [`HookEvent::IdlChanged`] for the programs inside the given directory whose IDLs have
+/// changed, copy the IDLs to the configured client directories and run the configured client
+/// generator.
+///
+/// Changed IDLs are compared with the previous ones for breaking changes. Denied breaking changes
+/// keep the previous IDL, so that they are reported again until they are fixed.
+///
+/// Returns whether there are denied breaking changes.
+pub async fn process_idls<F: Framework + ?Sized>(
+    framework: &F,
+    program_path: &Path,
+) -> miette::Result<bool> {
+    let deny = framework.config().idl.deny_breaking_changes;
+    let mut denied = false;
+    for (name, _) in framework.get_elf_paths(program_path).await {
+        let idl_path = get_idl_path(framework, &name);
+        let Ok(idl) = fs::read_to_string(&idl_path).await else {
+            continue;
+        };
+
+        let previous_idl = IDLS.lock().await.get(&idl_path).cloned();
+        if let Some(previous_idl) = previous_idl.filter(|previous_idl| *previous_idl != idl) {
+            let changes = match (
+                serde_json::from_str(&previous_idl),
+                serde_json::from_str(&idl),
+            ) {
+                (Ok(previous), Ok(current)) => diff_idls(&previous, &current),
+                _ => vec![],
+            };
+            let blocking = deny && !changes.is_empty();
+            for change in changes {
+                let breaking_change = BreakingChange {
+                    program: name.clone(),
+                    change,
+                    denied: deny,
+                };
+                let message = breaking_change.to_string();
+                print_diagnostic(breaking_change);
+                match deny {
+                    true => framework.emit(HookEvent::Error { message }).await?,
+                    false => framework.emit(HookEvent::Warning { message }).await?,
+                }
+            }
+            if blocking {
+                denied = true;
+                continue;
+            }
+
+            print_info(format!("IDL of {name} has changed"));
+            framework
+                .emit(HookEvent::IdlChanged {
+                    program: name.clone(),
+                    idl_path: idl_path.clone(),
+                })
+                .await?;
+        }
+
+        copy_to_client_dirs(framework, &name).await?;
+        generate_client(framework, &name, &idl_path, &idl).await?;
+        IDLS.lock().await.insert(idl_path, idl);
+    }
+
+    Ok(denied)
+}
+
+/// Find the breaking changes between the previous and the current IDL.
+///
+/// Breaking changes are removed or reordered fields of the accounts and the defined types,
+/// changed field types, changed discriminators, and removed instructions or changed instruction
+/// arguments.
+fn diff_idls(previous: &Value, current: &Value) -> Vec<String> {
+    let mut changes = vec![];
+
+    for (name, previous_account) in named_items(previous, "accounts") {
+        let Some(current_account) = find_named_item(current, "accounts", name) else {
+            changes.push(format!("account `{name}` was removed"));
+            continue;
+        };
+        if discriminator_changed(previous_account, current_account) {
+            changes.push(format!("discriminator of account `{name}` has changed"));
+        }
+        // Newer IDLs define the account layouts in `types` instead
+        diff_type(name, previous_account, current_account, &mut changes);
+    }
+
+    for (name, previous_type) in named_items(previous, "types") {
+        if let Some(current_type) = find_named_item(current, "types", name) {
+            diff_type(name, previous_type, current_type, &mut changes);
+        }
+    }
+
+    for (name, previous_ix) in named_items(previous, "instructions") {
+        let Some(current_ix) = find_named_item(current, "instructions", name) else {
+            changes.push(format!("instruction `{name}` was removed"));
+            continue;
+        };
+        if discriminator_changed(previous_ix, current_ix) {
+            changes.push(format!("discriminator of instruction `{name}` has changed"));
+        }
+        if previous_ix.get("args") != current_ix.get("args") {
+            changes.push(format!("arguments of instruction `{name}` have changed"));
+        }
+    }
+
+    changes
+}
+
+/// Find the breaking changes of the struct fields or the enum variants of a type definition.
+fn diff_type(name: &str, previous: &Value, current: &Value, changes: &mut Vec<String>) {
+    for key in ["fields", "variants"] {
+        let get_items = |value: &'_ Value| {
+            value
+                .get("type")
+                .and_then(|ty| ty.get(key))
+                .and_then(|items| items.as_array())
+                .cloned()
+        };
+        let (Some(previous_items), Some(current_items)) = (get_items(previous), get_items(current))
+        else {
+            continue;
+        };
+
+        for (index, previous_item) in previous_items.iter().enumerate() {
+            let item_name = previous_item
+                .get("name")
+                .and_then(|name| name.as_str())
+                .unwrap_or_default();
+            let position = current_items
+                .iter()
+                .position(|item| item.get("name") == previous_item.get("name"));
+            match position {
+                None => changes.push(format!("`{name}.{item_name}` was removed")),
+                Some(position) if position != index => {
+                    changes.push(format!("`{name}.{item_name}` was reordered"))
+                }
+                Some(position) if current_items[position] != *previous_item => {
+                    changes.push(format!("type of `{name}.{item_name}` has changed"))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns whether both items have a discriminator and they are different.
+fn discriminator_changed(previous: &Value, current: &Value) -> bool {
+    match (previous.get("discriminator"), current.get("discriminator")) {
+        (Some(previous), Some(current)) => previous != current,
+        _ => false,
+    }
+}
+
+/// Get the named items of an IDL array, e.g. the accounts.
+fn named_items<'a>(idl: &'a Value, key: &str) -> Vec<(&'a str, &'a Value)> {
+    idl.get(key)
+        .and_then(|items| items.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| Some((item.get("name")?.as_str()?, item)))
+        .collect()
+}
+
+/// Find the item with the given name in an IDL array.
+fn find_named_item<'a>(idl: &'a Value, key: &str, name: &str) -> Option<&'a Value> {
+    named_items(idl, key)
+        .into_iter()
+        .find(|(item_name, _)| *item_name == name)
+        .map(|(_, item)| item)
+}
+
+/// Copy the IDL and its TypeScript types to the configured client directories.
+async fn copy_to_client_dirs<F: Framework + ?Sized>(
+    framework: &F,
+    name: &str,
+) -> miette::Result<()> {
+    let program_name = ProgramName::new(name);
+    let types_path = framework
+        .origin()
+        .join(dirname::TARGET)
+        .join(dirname::TYPES)
+        .join(format!("{}.{}", program_name.snake_case(), extension::TS));
+    let sources = [get_idl_path(framework, name), types_path];
+
+    for client_dir in &framework.config().idl.client_dirs {
+        let client_dir = framework.origin().join(client_dir);
+        fs::create_dir_all(&client_dir).await.into_diagnostic()?;
+        for source in sources.iter().filter(|source| source.exists()) {
+            if let Some(file_name) = source.file_name() {
+                fs::copy(source, client_dir.join(file_name))
+                    .await
+                    .into_diagnostic()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the configured client generator with the IDL if the IDL content has changed since the
+/// client was last generated.
+///
+/// Failures are reported as warnings, and the generator is run again after the next build.
+async fn generate_client<F: Framework + ?Sized>(
+    framework: &F,
+    name: &str,
+    idl_path: &Path,
+    idl: &str,
+) -> miette::Result<()> {
+    let Some(generator) = &framework.config().idl.generator else {
+        return Ok(());
+    };
+
+    let mut hasher = DefaultHasher::new();
+    idl.hash(&mut hasher);
+    let hash = hasher.finish();
+    if GENERATED_IDLS.lock().await.get(idl_path) == Some(&hash) {
+        return Ok(());
+    }
+
+    let message = format!("Generating the client of {name}...");
+    let success_message = format!("Generated the client of {name}");
+    let error_message = format!("Could not generate the client of {name}");
+    let result = Progress::new()
+        .message(&message)
+        .success_message(&success_message)
+        .error_message(&error_message)
+        .spinner_with(|| async {
+            let output = WCommand::shell(generator)
+                .current_dir(framework.origin())
+                .env("WATCHSO_PROGRAM", name)
+                .env("WATCHSO_IDL_PATH", idl_path)
+                .output()
+                .await?;
+            match output.status().success() {
+                true => Ok(()),
+                false => Err(WatchError::GeneratorFailed(
+                    output.captured().unwrap_or_default(),
+                ))?,
+            }
+        })
+        .await;
+
+    match result {
+        Ok(()) => {
+            GENERATED_IDLS
+                .lock()
+                .await
+                .insert(idl_path.to_path_buf(), hash);
+            Ok(())
+        }
+        Err(e) => framework.warn(format!("{error_message}: {e}")).await,
+    }
+}
+
+/// Returns whether the IDL has changed since it was last uploaded to the given program id.
+pub async fn is_upload_needed(program_id: &str, idl: &str) -> bool {
+    UPLOADED_IDLS
+        .lock()
+        .await
+        .get(program_id)
+        .map_or(true, |uploaded_idl| uploaded_idl != idl)
+}
+
+/// Remember the IDL that was uploaded to the given program id.
+pub async fn set_uploaded(program_id: String, idl: String) {
+    UPLOADED_IDLS.lock().await.insert(program_id, idl);
+}
+
+/// Forget the uploaded and the previous IDLs after the ledger is reset, since there are no
+/// existing accounts or IDLs on-chain anymore.
+pub async fn reset() {
+    UPLOADED_IDLS.lock().await.clear();
+    IDLS.lock().await.clear();
+}
+
+/// Get the IDL path of the program.
+pub fn get_idl_path<F: Framework + ?Sized>(framework: &F, name: &str) -> PathBuf {
+    framework
+        .idl_path()
+        .join(ProgramName::new(name).idl_file_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Counter IDL generated by Anchor v0.30.
+    fn counter_idl() -> Value {
+        json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "counter", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [
+                {
+                    "name": "increment",
+                    "discriminator": [11, 18, 104, 9, 104, 174, 59, 33],
+                    "accounts": [{ "name": "counter", "writable": true }],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                },
+                {
+                    "name": "initialize",
+                    "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                    "accounts": [{ "name": "counter", "writable": true, "signer": true }],
+                    "args": []
+                }
+            ],
+            "accounts": [
+                { "name": "Counter", "discriminator": [255, 176, 4, 245, 188, 253, 124, 25] }
+            ],
+            "types": [
+                {
+                    "name": "Counter",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "authority", "type": "pubkey" },
+                            { "name": "count", "type": "u64" }
+                        ]
+                    }
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn diff_idls_without_breaking_changes() {
+        let previous = counter_idl();
+        let mut current = counter_idl();
+        current["types"][0]["type"]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "bump", "type": "u8" }));
+        current["instructions"].as_array_mut().unwrap().push(json!({
+            "name": "reset",
+            "discriminator": [23, 81, 251, 84, 138, 183, 240, 214],
+            "accounts": [],
+            "args": []
+        }));
+
+        assert!(diff_idls(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn diff_idls_with_breaking_changes() {
+        let previous = counter_idl();
+        let mut current = counter_idl();
+        current["types"][0]["type"]["fields"] = json!([
+            { "name": "count", "type": "u32" },
+            { "name": "authority", "type": "pubkey" }
+        ]);
+        current["instructions"][0]["args"] = json!([{ "name": "amount", "type": "u32" }]);
+        current["instructions"].as_array_mut().unwrap().remove(1);
+
+        assert_eq!(
+            diff_idls(&previous, &current),
+            vec![
+                "`Counter.authority` was reordered",
+                "`Counter.count` was reordered",
+                "arguments of instruction `increment` have changed",
+                "instruction `initialize` was removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_idls_with_removed_field_and_account() {
+        let previous = counter_idl();
+        let mut current = counter_idl();
+        current["types"][0]["type"]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .truncate(1);
+        current["accounts"] = json!([]);
+
+        assert_eq!(
+            diff_idls(&previous, &current),
+            vec![
+                "account `Counter` was removed",
+                "`Counter.count` was removed"
+            ]
+        );
+    }
+}
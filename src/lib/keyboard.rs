@@ -0,0 +1,167 @@
+//! Interactive keyboard commands while watching.
+
+use std::{
+    io::{self, IsTerminal},
+    process::{Command, Stdio},
+    sync::Arc,
+    thread,
+};
+
+use console::{style, Term};
+use crossterm::{
+    event::{self, Event as TermEvent, KeyCode, KeyEventKind},
+    terminal,
+};
+use miette::IntoDiagnostic;
+use tokio::sync::mpsc;
+use watchexec::{
+    event::{Event, Priority, Tag},
+    signal::source::MainSignal,
+    Watchexec,
+};
+
+use crate::{
+    control::{self, ControlRequest, ControlResponse},
+    fixtures,
+    framework::WatchableFramework,
+    framework_utils::restart_test_validator,
+    logs,
+    output::{print_error, print_info},
+    progress::Progress,
+};
+
+/// Help line that lists the available keys.
+const HELP: &str = "r rebuild all | d redeploy all | p pause/resume | v restart validator with \
+reset | l toggle program logs | c clear screen | h help | q quit";
+
+/// Restores the original terminal mode when dropped.
+pub struct KeyInputGuard;
+
+impl Drop for KeyInputGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Enable reading the keys as soon as they are pressed, without echoing them.
+///
+/// Unlike raw mode, this keeps the output processing and signals, e.g. `Ctrl+C`, as is, which
+/// means the inherited output of the commands is not affected. Raw mode doesn't allow this, so
+/// they are enabled again with `stty` after entering it.
+///
+/// Returns `None` if stdin is not a terminal.
+pub fn enable_key_input() -> Option<KeyInputGuard> {
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+
+    terminal::enable_raw_mode().ok()?;
+    let guard = KeyInputGuard;
+    let success = Command::new("stty")
+        .args(["opost", "onlcr", "isig"])
+        .stdin(Stdio::inherit())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or_default();
+
+    // Guard restores the original mode if `stty` has failed
+    success.then_some(guard)
+}
+
+/// Listen to the key presses and run the matching commands.
+///
+/// [`enable_key_input`] should be called beforehand.
+pub async fn listen(framework: Arc<dyn WatchableFramework>, watchexec: Arc<Watchexec>) {
+    // Reading stdin blocks, using a thread instead of a blocking task to not block the runtime
+    // from shutting down
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if let TermEvent::Key(key) = event {
+                if let (KeyCode::Char(key), KeyEventKind::Press) = (key.code, key.kind) {
+                    if tx.send(key).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    print_help();
+
+    while let Some(key) = rx.recv().await {
+        let result = match key.to_ascii_lowercase() {
+            'r' => request(framework.as_ref(), ControlRequest::Build { program: None }).await,
+            'd' => request(framework.as_ref(), ControlRequest::Deploy { program: None }).await,
+            'p' => toggle_pause(),
+            'v' => restart_validator(framework.as_ref()).await,
+            'l' => {
+                logs::toggle(framework.clone()).await;
+                Ok(())
+            }
+            'c' => Term::stdout().clear_screen().into_diagnostic(),
+            'h' | '?' => {
+                print_help();
+                Ok(())
+            }
+            'q' => {
+                let event = Event {
+                    tags: vec![Tag::Signal(MainSignal::Interrupt)],
+                    ..Default::default()
+                };
+                watchexec
+                    .send_event(event, Priority::Urgent)
+                    .await
+                    .into_diagnostic()
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(err) = result {
+            print_error(err);
+        }
+    }
+}
+
+/// Print the available keys.
+fn print_help() {
+    println!("{}", style(HELP).dim());
+}
+
+/// Dispatch the request as if it was sent to the control server.
+async fn request(
+    framework: &dyn WatchableFramework,
+    request: ControlRequest,
+) -> miette::Result<()> {
+    match control::dispatch(framework, request).await {
+        ControlResponse::Error { message } => Err(miette::miette!(message)),
+        _ => Ok(()),
+    }
+}
+
+/// Pause or resume watching.
+fn toggle_pause() -> miette::Result<()> {
+    let paused = !control::is_paused();
+    control::set_paused(paused);
+    match paused {
+        true => print_info("Paused watching"),
+        false => print_info("Resumed watching"),
+    }
+
+    Ok(())
+}
+
+/// Restart the test validator with reset and redeploy all programs.
+async fn restart_validator(framework: &dyn WatchableFramework) -> miette::Result<()> {
+    Progress::new()
+        .message("Restarting Solana test validator...")
+        .success_message("Restarted Solana test validator")
+        .error_message("Could not restart Solana test validator")
+        .spinner_with(|| async {
+            restart_test_validator(framework.origin(), framework.config()).await
+        })
+        .await?;
+
+    request(framework, ControlRequest::Deploy { program: None }).await?;
+    fixtures::seed(framework).await
+}
@@ -10,13 +10,26 @@
 
 pub mod action;
 pub mod command;
+pub mod config;
 pub mod constants;
+pub mod control;
 pub mod error;
+pub mod fixtures;
 pub mod framework;
 pub mod framework_utils;
 pub mod glob;
+pub mod hook;
+pub mod idl;
+pub mod logs;
+pub mod output;
+pub mod program_error;
 pub mod progress;
+pub mod report;
+pub mod sbf;
 pub mod toml;
 
+#[cfg(unix)]
+mod keyboard;
+mod tui;
 mod watch;
 pub use watch::watch;
@@ -0,0 +1,471 @@
+//! Stream the program logs from the validator's `logsSubscribe` websocket.
+//!
+//! Only the logs that mention the program ids in the [`ProjectMap`](crate::framework_utils::ProjectMap)
+//! are streamed, the subscriptions are updated after the program ids are updated and after the
+//! deployments. Logs are printed in human output format and emitted as [`HookEvent::ProgramLog`]
+//! in all output formats.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use console::{style, Color};
+use futures_util::{SinkExt, StreamExt};
+use lazy_static::lazy_static;
+use miette::IntoDiagnostic;
+use serde::Deserialize;
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+    time,
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{
+    command::WCommand,
+    framework::WatchableFramework,
+    framework_utils::{get_pubkey_from_keypair_path, ProgramName},
+    hook::{HookEvent, HookKind},
+    output::{print_error, print_info, OutputFormat},
+    program_error::ErrorDecoder,
+    report,
+};
+
+lazy_static! {
+    /// Running log stream task.
+    static ref LOG_STREAM: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+    /// Notified when the program ids might have changed, e.g. after a deployment.
+    static ref RESUBSCRIBE: Notify = Notify::new();
+}
+
+/// Whether the hooks that notify [`RESUBSCRIBE`] have been subscribed.
+static RESUBSCRIBE_HOOKS: AtomicBool = AtomicBool::new(false);
+
+/// Websocket URL of the local test validator.
+const DEFAULT_WEBSOCKET_URL: &str = "ws://127.0.0.1:8900";
+
+/// Interval of reconnecting after the connection is closed, e.g. the validator has restarted.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Colors of the program names, picked by the program's index in alphabetical order.
+const PROGRAM_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+];
+
+/// Websocket connection to the validator.
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Message received from the websocket.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WsMessage {
+    /// Subscription request was successful.
+    Subscribed {
+        /// Request id
+        id: u64,
+        /// Subscription id
+        result: u64,
+    },
+    /// Logs notification.
+    Notification {
+        /// Notification params
+        params: NotificationParams,
+    },
+    /// Request has failed.
+    Error {
+        /// RPC error
+        error: RpcError,
+    },
+}
+
+/// Params of the `logsNotification`.
+#[derive(Deserialize)]
+struct NotificationParams {
+    /// Subscription id
+    subscription: u64,
+    /// Notification result
+    result: NotificationResult,
+}
+
+/// Result of the `logsNotification`.
+#[derive(Deserialize)]
+struct NotificationResult {
+    /// Transaction logs
+    value: TransactionLogs,
+}
+
+/// Logs of a transaction.
+#[derive(Deserialize)]
+struct TransactionLogs {
+    /// Transaction signature
+    signature: String,
+    /// Transaction error, `null` if the transaction was successful
+    err: Option<serde_json::Value>,
+    /// Log messages
+    logs: Vec<String>,
+}
+
+/// JSON RPC error.
+#[derive(Deserialize)]
+struct RpcError {
+    /// Error message
+    message: String,
+}
+
+/// Program that has been subscribed to.
+struct SubscribedProgram {
+    /// Program name
+    name: String,
+    /// Program id
+    program_id: String,
+    /// Color of the program name
+    color: Color,
+}
+
+/// Log subscriptions of a websocket connection.
+#[derive(Default)]
+struct Subscriptions {
+    /// Subscribed programs by their request ids
+    programs: HashMap<u64, SubscribedProgram>,
+    /// Request ids by the subscription ids
+    request_ids: HashMap<u64, u64>,
+    /// Id of the next request
+    next_id: u64,
+}
+
+impl Subscriptions {
+    /// Subscribe to the logs of the programs that have a keypair and unsubscribe from the
+    /// programs that have been removed or whose program id has changed.
+    async fn update(
+        &mut self,
+        ws: &mut WsStream,
+        framework: &dyn WatchableFramework,
+    ) -> miette::Result<()> {
+        let mut names = framework
+            .project_map()
+            .get_all()
+            .await
+            .into_keys()
+            .collect::<Vec<_>>();
+        names.sort();
+
+        let mut programs = vec![];
+        for (index, name) in names.into_iter().enumerate() {
+            let keypair_path = framework
+                .deploy_path()
+                .join(ProgramName::new(&name).keypair_file_name());
+            if keypair_path.exists() {
+                let program_id = get_pubkey_from_keypair_path(keypair_path).await?;
+                let color = PROGRAM_COLORS[index % PROGRAM_COLORS.len()];
+                programs.push(SubscribedProgram {
+                    name,
+                    program_id,
+                    color,
+                });
+            }
+        }
+
+        let stale_request_ids = self
+            .programs
+            .iter()
+            .filter(|(_, subscribed)| {
+                !programs.iter().any(|program| {
+                    program.name == subscribed.name && program.program_id == subscribed.program_id
+                })
+            })
+            .map(|(request_id, _)| *request_id)
+            .collect::<Vec<_>>();
+        for request_id in stale_request_ids {
+            self.programs.remove(&request_id);
+            let subscription = self
+                .request_ids
+                .iter()
+                .find(|(_, id)| **id == request_id)
+                .map(|(subscription, _)| *subscription);
+            if let Some(subscription) = subscription {
+                self.request_ids.remove(&subscription);
+                self.unsubscribe(ws, subscription).await?;
+            }
+        }
+
+        // `mentions` filter only supports a single program id, subscribe to each program
+        // separately
+        for program in programs {
+            let is_subscribed = self.programs.values().any(|subscribed| {
+                subscribed.name == program.name && subscribed.program_id == program.program_id
+            });
+            if is_subscribed {
+                continue;
+            }
+
+            let id = self.next_id();
+            let request = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "logsSubscribe",
+                "params": [{ "mentions": [program.program_id] }, { "commitment": "confirmed" }],
+            });
+            ws.send(Message::Text(request.to_string()))
+                .await
+                .into_diagnostic()?;
+            self.programs.insert(id, program);
+        }
+
+        Ok(())
+    }
+
+    /// Save the subscription id of a successful subscription request.
+    ///
+    /// The subscription is cancelled if the program has been unsubscribed before the response.
+    async fn on_subscribed(
+        &mut self,
+        ws: &mut WsStream,
+        request_id: u64,
+        subscription: u64,
+    ) -> miette::Result<()> {
+        match self.programs.contains_key(&request_id) {
+            true => {
+                self.request_ids.insert(subscription, request_id);
+                Ok(())
+            }
+            false => self.unsubscribe(ws, subscription).await,
+        }
+    }
+
+    /// Get the subscribed program of the subscription.
+    fn get(&self, subscription: u64) -> Option<&SubscribedProgram> {
+        self.request_ids
+            .get(&subscription)
+            .and_then(|request_id| self.programs.get(request_id))
+    }
+
+    /// Send a `logsUnsubscribe` request.
+    async fn unsubscribe(&mut self, ws: &mut WsStream, subscription: u64) -> miette::Result<()> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id(),
+            "method": "logsUnsubscribe",
+            "params": [subscription],
+        });
+        ws.send(Message::Text(request.to_string()))
+            .await
+            .into_diagnostic()
+    }
+
+    /// Get a new request id.
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+/// Returns whether the program logs are being streamed.
+pub async fn is_streaming() -> bool {
+    LOG_STREAM.lock().await.is_some()
+}
+
+/// Start streaming the program logs.
+///
+/// This won't have any effect if the logs are already being streamed. The connection is retried
+/// until [`stop`] is called.
+pub async fn start(framework: Arc<dyn WatchableFramework>) {
+    let mut log_stream = LOG_STREAM.lock().await;
+    if log_stream.is_some() {
+        return;
+    }
+
+    // Programs can be added and program ids can change while watching
+    if !RESUBSCRIBE_HOOKS.swap(true, Ordering::Relaxed) {
+        for kind in [HookKind::ProgramIdUpdated, HookKind::AfterDeploy] {
+            framework
+                .hooks()
+                .subscribe(kind, |_| {
+                    RESUBSCRIBE.notify_one();
+                    Ok(())
+                })
+                .await;
+        }
+    }
+
+    let websocket_url = get_websocket_url(framework.as_ref()).await;
+    *log_stream = Some(tokio::spawn(async move {
+        let mut warned = false;
+        loop {
+            match stream(framework.as_ref(), &websocket_url).await {
+                Ok(()) => warned = false,
+                Err(err) if !warned => {
+                    warned = true;
+                    let _ = framework
+                        .warn(format!(
+                            "Could not stream program logs from {websocket_url}, retrying: {err}"
+                        ))
+                        .await;
+                }
+                Err(_) => (),
+            }
+
+            time::sleep(RECONNECT_INTERVAL).await;
+        }
+    }));
+}
+
+/// Stop streaming the program logs.
+pub async fn stop() {
+    if let Some(log_stream) = LOG_STREAM.lock().await.take() {
+        log_stream.abort();
+    }
+}
+
+/// Start or stop streaming the program logs.
+///
+/// Returns whether the logs are being streamed after the toggle.
+pub async fn toggle(framework: Arc<dyn WatchableFramework>) -> bool {
+    match is_streaming().await {
+        true => {
+            stop().await;
+            print_info("Stopped program logs");
+            false
+        }
+        false => {
+            start(framework).await;
+            print_info("Started program logs");
+            true
+        }
+    }
+}
+
+/// Get the websocket URL from the config file or the Solana CLI config.
+async fn get_websocket_url(framework: &dyn WatchableFramework) -> String {
+    if let Some(websocket_url) = &framework.config().logs.websocket_url {
+        return websocket_url.to_owned();
+    }
+
+    // Output includes a line such as `WebSocket URL: ws://localhost:8900/ (computed)`
+    WCommand::new("solana config get")
+        .output()
+        .await
+        .ok()
+        .and_then(|output| {
+            output.stdout().lines().find_map(|line| {
+                line.strip_prefix("WebSocket URL:")
+                    .and_then(|url| url.split_whitespace().next())
+                    .map(|url| url.to_owned())
+            })
+        })
+        .unwrap_or_else(|| DEFAULT_WEBSOCKET_URL.into())
+}
+
+/// Subscribe to the logs of the programs and handle the notifications until the connection is
+/// closed.
+///
+/// Subscriptions are updated whenever the program ids might have changed.
+async fn stream(framework: &dyn WatchableFramework, websocket_url: &str) -> miette::Result<()> {
+    let (mut ws, _) = connect_async(websocket_url).await.into_diagnostic()?;
+    let mut subscriptions = Subscriptions::default();
+    subscriptions.update(&mut ws, framework).await?;
+
+    loop {
+        let message = tokio::select! {
+            _ = RESUBSCRIBE.notified() => {
+                subscriptions.update(&mut ws, framework).await?;
+                continue;
+            }
+            message = ws.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+        };
+        let Message::Text(text) = message.into_diagnostic()? else {
+            continue;
+        };
+
+        match serde_json::from_str(&text) {
+            Ok(WsMessage::Subscribed { id, result }) => {
+                subscriptions.on_subscribed(&mut ws, id, result).await?;
+            }
+            Ok(WsMessage::Notification { params }) => {
+                let Some(program) = subscriptions.get(params.subscription) else {
+                    continue;
+                };
+                let mut logs = params.result.value;
+                let success = logs.err.is_none();
+                if !success {
+                    // Load the errors on failure to always use the latest IDLs
+                    let decoder = ErrorDecoder::load(framework).await;
+                    for log in logs.logs.iter_mut() {
+                        *log = decoder.decode(log).into_owned();
+                    }
+                }
+
+                if framework.config().report.compute_units {
+                    if let Err(err) = report::report_compute_units(
+                        framework,
+                        &program.name,
+                        &program.program_id,
+                        &logs.logs,
+                    )
+                    .await
+                    {
+                        print_error(err);
+                    }
+                }
+
+                let event = HookEvent::ProgramLog {
+                    program: program.name.to_owned(),
+                    program_id: program.program_id.to_owned(),
+                    signature: logs.signature,
+                    success,
+                    logs: logs.logs,
+                };
+
+                if OutputFormat::is_human() {
+                    print_program_log(&event, program.color);
+                }
+                if let Err(err) = framework.emit(event).await {
+                    print_error(err);
+                }
+            }
+            Ok(WsMessage::Error { error }) => return Err(miette::miette!(error.message)),
+            Err(_) => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the logs prefixed with the program name in the given color.
+fn print_program_log(event: &HookEvent, color: Color) {
+    let HookEvent::ProgramLog {
+        program,
+        signature,
+        success,
+        logs,
+        ..
+    } = event
+    else {
+        return;
+    };
+
+    let prefix = style(format!("[{program}]")).fg(color).bold();
+    match success {
+        true => println!("{prefix} {}", style(signature).dim()),
+        false => println!(
+            "{prefix} {} {}",
+            style(signature).dim(),
+            style("failed").red()
+        ),
+    }
+    for log in logs {
+        println!("{prefix} {log}");
+    }
+}
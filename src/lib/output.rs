@@ -0,0 +1,82 @@
+//! Output format of the watch process.
+
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use clap::ValueEnum;
+use console::style;
+use miette::Diagnostic;
+
+/// Current output format. Stored as `u8` to be able to use an atomic.
+static FORMAT: AtomicU8 = AtomicU8::new(OutputFormat::Human as u8);
+
+/// Output format of the watch process.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Progress bars, spinners and the inherited command output.
+    #[default]
+    Human = 0,
+    /// Newline-delimited JSON events.
+    ///
+    /// Human readable output is disabled and the command output is captured, events should be
+    /// printed by subscribing to [`Hooks`](crate::hook::Hooks).
+    Json = 1,
+    /// Full-screen dashboard with a program table, the validator status and the logs.
+    ///
+    /// Human readable output is disabled and the command output is captured, the dashboard is
+    /// fed by the same events as [`OutputFormat::Json`].
+    Tui = 2,
+}
+
+impl OutputFormat {
+    /// Get the current output format.
+    pub fn get() -> Self {
+        match FORMAT.load(Ordering::Relaxed) {
+            1 => Self::Json,
+            2 => Self::Tui,
+            _ => Self::Human,
+        }
+    }
+
+    /// Set the current output format.
+    ///
+    /// This should be set before watching starts.
+    pub fn set(self) {
+        FORMAT.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// Returns whether the current output format is [`OutputFormat::Human`].
+    pub fn is_human() -> bool {
+        Self::get() == Self::Human
+    }
+}
+
+/// Print the info message if the output format is [`OutputFormat::Human`].
+pub fn print_info<D: Display>(msg: D) {
+    if OutputFormat::is_human() {
+        println!("{} {}", style("[INFO]").blue().bold(), msg);
+    }
+}
+
+/// Print the error message if the output format is [`OutputFormat::Human`].
+pub fn print_error<D: Display>(msg: D) {
+    if OutputFormat::is_human() {
+        eprintln!("{} {}", style("[ERR]").red().bold(), msg);
+    }
+}
+
+/// Print the warning message if the output format is [`OutputFormat::Human`].
+pub fn print_warning<D: Display>(msg: D) {
+    if OutputFormat::is_human() {
+        eprintln!("{} {}", style("[WARN]").yellow().bold(), msg);
+    }
+}
+
+/// Print the diagnostic with its help message if the output format is [`OutputFormat::Human`].
+pub fn print_diagnostic<D: Diagnostic + Send + Sync + 'static>(diagnostic: D) {
+    if OutputFormat::is_human() {
+        eprintln!("{:?}", miette::Report::new(diagnostic));
+    }
+}
@@ -0,0 +1,416 @@
+//! Decode the custom program error codes, e.g. `custom program error: 0x1771`.
+//!
+//! Errors are read from the program IDLs in `target/idl`, or from the Rust error enums that are
+//! configured in the `[errors]` table of the config file.
+
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+use lazy_static::lazy_static;
+use miette::IntoDiagnostic;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::{
+    framework::Framework,
+    framework_utils::{get_pubkey_from_keypair_path, ProgramName},
+};
+
+/// Anchor framework error codes, which are shared by all Anchor programs.
+#[rustfmt::skip]
+const ANCHOR_ERRORS: &[(u32, &str, &str)] = &[
+    (100, "InstructionMissing", "8 byte instruction identifier not provided"),
+    (101, "InstructionFallbackNotFound", "Fallback functions are not supported"),
+    (102, "InstructionDidNotDeserialize", "The program could not deserialize the given instruction"),
+    (103, "InstructionDidNotSerialize", "The program could not serialize the given instruction"),
+    (1000, "IdlInstructionStub", "The program was compiled without idl instructions"),
+    (1001, "IdlInstructionInvalidProgram", "Invalid program given to the IDL instruction"),
+    (2000, "ConstraintMut", "A mut constraint was violated"),
+    (2001, "ConstraintHasOne", "A has one constraint was violated"),
+    (2002, "ConstraintSigner", "A signer constraint was violated"),
+    (2003, "ConstraintRaw", "A raw constraint was violated"),
+    (2004, "ConstraintOwner", "An owner constraint was violated"),
+    (2005, "ConstraintRentExempt", "A rent exemption constraint was violated"),
+    (2006, "ConstraintSeeds", "A seeds constraint was violated"),
+    (2007, "ConstraintExecutable", "An executable constraint was violated"),
+    (2009, "ConstraintAssociated", "An associated constraint was violated"),
+    (2010, "ConstraintAssociatedInit", "An associated init constraint was violated"),
+    (2011, "ConstraintClose", "A close constraint was violated"),
+    (2012, "ConstraintAddress", "An address constraint was violated"),
+    (2013, "ConstraintZero", "Expected zero account discriminant"),
+    (2014, "ConstraintTokenMint", "A token mint constraint was violated"),
+    (2015, "ConstraintTokenOwner", "A token owner constraint was violated"),
+    (2016, "ConstraintMintMintAuthority", "A mint mint authority constraint was violated"),
+    (2017, "ConstraintMintFreezeAuthority", "A mint freeze authority constraint was violated"),
+    (2018, "ConstraintMintDecimals", "A mint decimals constraint was violated"),
+    (2019, "ConstraintSpace", "A space constraint was violated"),
+    (2020, "ConstraintAccountIsNone", "A required account for the constraint is None"),
+    (2500, "RequireViolated", "A require expression was violated"),
+    (2501, "RequireEqViolated", "A require_eq expression was violated"),
+    (2502, "RequireKeysEqViolated", "A require_keys_eq expression was violated"),
+    (2503, "RequireNeqViolated", "A require_neq expression was violated"),
+    (2504, "RequireKeysNeqViolated", "A require_keys_neq expression was violated"),
+    (2505, "RequireGtViolated", "A require_gt expression was violated"),
+    (2506, "RequireGteViolated", "A require_gte expression was violated"),
+    (3000, "AccountDiscriminatorAlreadySet", "The account discriminator was already set on this account"),
+    (3001, "AccountDiscriminatorNotFound", "No 8 byte discriminator was found on the account"),
+    (3002, "AccountDiscriminatorMismatch", "8 byte discriminator did not match what was expected"),
+    (3003, "AccountDidNotDeserialize", "Failed to deserialize the account"),
+    (3004, "AccountDidNotSerialize", "Failed to serialize the account"),
+    (3005, "AccountNotEnoughKeys", "Not enough account keys given to the instruction"),
+    (3006, "AccountNotMutable", "The given account is not mutable"),
+    (3007, "AccountOwnedByWrongProgram", "The given account is owned by a different program than expected"),
+    (3008, "InvalidProgramId", "Program ID was not as expected"),
+    (3009, "InvalidProgramExecutable", "Program account is not executable"),
+    (3010, "AccountNotSigner", "The given account did not sign"),
+    (3011, "AccountNotSystemOwned", "The given account is not owned by the system program"),
+    (3012, "AccountNotInitialized", "The program expected this account to be already initialized"),
+    (3013, "AccountNotProgramData", "The given account is not a program data account"),
+    (3014, "AccountNotAssociatedTokenAccount", "The given account is not the associated token account"),
+    (3015, "AccountSysvarMismatch", "The given public key does not match the required sysvar"),
+    (3016, "AccountReallocExceedsLimit", "The account reallocation exceeds the MAX_PERMITTED_DATA_INCREASE limit"),
+    (3017, "AccountDuplicateReallocs", "The account was duplicated for more than one reallocation"),
+    (4100, "DeclaredProgramIdMismatch", "The declared program id does not match the actual program id"),
+    (5000, "Deprecated", "The API being used is deprecated and should no longer be used"),
+];
+
+/// Custom error of a program.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProgramError {
+    /// Error code
+    pub code: u32,
+    /// Error name
+    pub name: String,
+    /// Error message
+    pub msg: Option<String>,
+}
+
+/// Errors of a program.
+#[derive(Debug, Default)]
+struct ProgramErrors {
+    /// Errors by their codes
+    errors: HashMap<u32, ProgramError>,
+    /// Whether the program is an Anchor program, which means it also includes the Anchor
+    /// framework errors
+    anchor: bool,
+}
+
+/// Subset of the IDL that includes the errors.
+#[derive(Deserialize)]
+struct Idl {
+    /// Custom errors
+    #[serde(default)]
+    errors: Vec<ProgramError>,
+    /// IDL metadata
+    #[serde(default)]
+    metadata: IdlMetadata,
+}
+
+/// Subset of the IDL metadata.
+#[derive(Default, Deserialize)]
+struct IdlMetadata {
+    /// Generator of the IDL, e.g. `shank`
+    origin: Option<String>,
+}
+
+/// Decoder of the custom program errors.
+#[derive(Debug, Default)]
+pub struct ErrorDecoder {
+    /// Program errors by the program ids
+    programs: HashMap<String, ProgramErrors>,
+}
+
+impl ErrorDecoder {
+    /// Load the errors of all programs.
+    ///
+    /// The configured error enum source takes precedence over the IDL. Programs without a program
+    /// keypair or an error source are skipped.
+    pub async fn load<F: Framework + ?Sized>(framework: &F) -> Self {
+        let mut programs = HashMap::new();
+        for name in framework.project_map().get_all().await.into_keys() {
+            let program_name = ProgramName::new(&name);
+            let keypair_path = framework
+                .deploy_path()
+                .join(program_name.keypair_file_name());
+            if !keypair_path.exists() {
+                continue;
+            }
+            let Ok(program_id) = get_pubkey_from_keypair_path(keypair_path).await else {
+                continue;
+            };
+
+            let errors = match framework.config().errors.sources.get(&name) {
+                Some(source_path) => read_error_enum(framework.origin().join(source_path)).await,
+                None => {
+                    read_idl_errors(framework.idl_path().join(program_name.idl_file_name())).await
+                }
+            };
+
+            if let Ok(errors) = errors {
+                programs.insert(program_id, errors);
+            }
+        }
+
+        Self { programs }
+    }
+
+    /// Get the error with the given code.
+    ///
+    /// If the program id is not known, e.g. the log line doesn't include it, all programs are
+    /// searched.
+    pub fn get(&self, program_id: Option<&str>, code: u32) -> Option<ProgramError> {
+        match program_id {
+            Some(program_id) => get_error(self.programs.get(program_id)?, code),
+            None => self
+                .programs
+                .values()
+                .find_map(|errors| get_error(errors, code)),
+        }
+    }
+
+    /// Append the error name and message to the custom program errors in the given text.
+    pub fn decode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        lazy_static! {
+            static ref CUSTOM_ERROR_REGEX: Regex =
+                Regex::new(r"(?:Program (\w+) failed: )?custom program error: 0x([0-9a-fA-F]+)")
+                    .unwrap();
+        }
+
+        CUSTOM_ERROR_REGEX.replace_all(text, |captures: &Captures| {
+            let program_id = captures.get(1).map(|m| m.as_str());
+            let error = u32::from_str_radix(&captures[2], 16)
+                .ok()
+                .and_then(|code| self.get(program_id, code));
+            match error {
+                Some(ProgramError {
+                    name,
+                    msg: Some(msg),
+                    ..
+                }) => format!("{} ({name}: {msg})", &captures[0]),
+                Some(ProgramError { name, .. }) => format!("{} ({name})", &captures[0]),
+                None => captures[0].to_owned(),
+            }
+        })
+    }
+}
+
+/// Get the error of the program, including the Anchor framework errors for Anchor programs.
+fn get_error(errors: &ProgramErrors, code: u32) -> Option<ProgramError> {
+    if let Some(error) = errors.errors.get(&code) {
+        return Some(error.to_owned());
+    }
+    if !errors.anchor {
+        return None;
+    }
+
+    ANCHOR_ERRORS
+        .iter()
+        .find(|(anchor_code, ..)| *anchor_code == code)
+        .map(|(code, name, msg)| ProgramError {
+            code: *code,
+            name: (*name).into(),
+            msg: Some((*msg).into()),
+        })
+}
+
+/// Read the custom errors from the IDL.
+///
+/// IDLs that are not generated by Shank are Anchor IDLs.
+async fn read_idl_errors<P: AsRef<Path>>(idl_path: P) -> miette::Result<ProgramErrors> {
+    let idl: Idl = serde_json::from_str(&fs::read_to_string(idl_path).await.into_diagnostic()?)
+        .into_diagnostic()?;
+
+    Ok(ProgramErrors {
+        errors: idl
+            .errors
+            .into_iter()
+            .map(|error| (error.code, error))
+            .collect(),
+        anchor: idl.metadata.origin.as_deref() != Some("shank"),
+    })
+}
+
+/// Read the custom errors from the error enum of the Rust file.
+async fn read_error_enum<P: AsRef<Path>>(source_path: P) -> miette::Result<ProgramErrors> {
+    let source = fs::read_to_string(source_path).await.into_diagnostic()?;
+    parse_error_enum(&source)
+}
+
+/// Parse the custom errors from the error enum of the Rust source.
+///
+/// The enum that derives `Error` or has the `#[error_code]` attribute is preferred, otherwise the
+/// first enum is used. The error message is taken from the `#[error("...")]` or `#[msg("...")]`
+/// attribute or the doc comment of the variant, and the code from the explicit discriminant if it
+/// exists. Codes of the Anchor `#[error_code]` enums start from the `offset` argument, `6000` by
+/// default, and they also include the Anchor framework errors.
+fn parse_error_enum(source: &str) -> miette::Result<ProgramErrors> {
+    /// Default offset of the Anchor `#[error_code]` enums.
+    const ANCHOR_ERROR_CODE_OFFSET: u32 = 6000;
+
+    lazy_static! {
+        static ref ENUM_REGEX: Regex = Regex::new(
+            r"(?m)^([ \t]*)((?:(?:#\[.*\]|///.*)\s*)*)(?:pub(?:\([^)]*\))?\s+)?enum\s+\w+[^{;]*\{"
+        )
+        .unwrap();
+        static ref ERROR_ENUM_ATTR_REGEX: Regex =
+            Regex::new(r"#\[error_code\b|derive\([^)]*\bError\b").unwrap();
+        static ref ERROR_CODE_ATTR_REGEX: Regex =
+            Regex::new(r"#\[error_code(?:\(\s*offset\s*=\s*(\d+)\s*\))?\]").unwrap();
+        static ref ERROR_ATTR_REGEX: Regex =
+            Regex::new(r#"^#\[(?:error|msg)\("((?:[^"\\]|\\.)*)""#).unwrap();
+        static ref VARIANT_REGEX: Regex =
+            Regex::new(r"^([A-Z]\w*)\b(?:[^=]*=\s*(0x[0-9a-fA-F]+|\d+))?").unwrap();
+    }
+
+    // The closing brace of the enum has the same indentation as the enum
+    let enums = ENUM_REGEX.captures_iter(source).filter_map(|captures| {
+        let start = captures.get(0)?.end();
+        let end = source[start..].find(&format!("\n{}}}", &captures[1]))?;
+        let attrs = captures.get(2)?.as_str();
+        Some((attrs, &source[start..start + end]))
+    });
+    let mut error_enum = None;
+    for (attrs, enum_body) in enums {
+        if ERROR_ENUM_ATTR_REGEX.is_match(attrs) {
+            error_enum = Some((attrs, enum_body));
+            break;
+        }
+        error_enum.get_or_insert((attrs, enum_body));
+    }
+    let Some((attrs, body)) = error_enum else {
+        return Ok(ProgramErrors::default());
+    };
+
+    let offset = match ERROR_CODE_ATTR_REGEX.captures(attrs) {
+        Some(captures) => match captures.get(1) {
+            Some(offset) => Some(offset.as_str().parse().into_diagnostic()?),
+            None => Some(ANCHOR_ERROR_CODE_OFFSET),
+        },
+        None => None,
+    };
+
+    let mut errors = HashMap::new();
+    let mut code = 0;
+    let mut msg = None;
+    for line in body.lines().map(|line| line.trim()) {
+        if let Some(captures) = ERROR_ATTR_REGEX.captures(line) {
+            msg = Some(captures[1].to_owned());
+        } else if let Some(doc) = line.strip_prefix("///") {
+            msg.get_or_insert_with(|| doc.trim().to_owned());
+        } else if let Some(captures) = VARIANT_REGEX.captures(line) {
+            if let Some(discriminant) = captures.get(2).map(|m| m.as_str()) {
+                code = match discriminant.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => discriminant.parse(),
+                }
+                .into_diagnostic()?;
+            }
+
+            let error_code = code + offset.unwrap_or_default();
+            let name = captures[1].to_owned();
+            errors.insert(
+                error_code,
+                ProgramError {
+                    code: error_code,
+                    name,
+                    msg: msg.take(),
+                },
+            );
+            code += 1;
+        }
+    }
+
+    Ok(ProgramErrors {
+        errors,
+        anchor: offset.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_enum_prefers_error_enum() {
+        let source = r#"
+use thiserror::Error;
+
+pub enum Instruction {
+    Initialize,
+    Increment,
+}
+
+pub mod error {
+    use super::*;
+
+    #[derive(Error, Debug, Copy, Clone)]
+    pub enum CounterError {
+        /// Invalid instruction
+        #[error("Invalid instruction")]
+        InvalidInstruction = 0x1770,
+        /// Counter overflowed
+        Overflow,
+    }
+}
+"#;
+
+        let ProgramErrors { errors, anchor } = parse_error_enum(source).unwrap();
+        assert!(!anchor);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[&0x1770].name, "InvalidInstruction");
+        assert_eq!(errors[&0x1770].msg.as_deref(), Some("Invalid instruction"));
+        assert_eq!(errors[&0x1771].name, "Overflow");
+        assert_eq!(errors[&0x1771].msg.as_deref(), Some("Counter overflowed"));
+    }
+
+    #[test]
+    fn parse_error_enum_falls_back_to_first_enum() {
+        let source = "pub enum MyError {\n    NotOwner,\n    NotSigner = 5,\n}\n";
+
+        let errors = parse_error_enum(source).unwrap().errors;
+        assert_eq!(errors[&0].name, "NotOwner");
+        assert_eq!(errors[&5].name, "NotSigner");
+    }
+
+    #[test]
+    fn parse_error_enum_anchor_error_code() {
+        let source = r#"
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Amount must be positive")]
+    InvalidAmount,
+    Overflow,
+}
+"#;
+
+        let program_errors = parse_error_enum(source).unwrap();
+        assert!(program_errors.anchor);
+        let errors = &program_errors.errors;
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[&0x1770].name, "InvalidAmount");
+        assert_eq!(
+            errors[&0x1770].msg.as_deref(),
+            Some("Amount must be positive")
+        );
+        assert_eq!(errors[&0x1771].name, "Overflow");
+        assert_eq!(
+            get_error(&program_errors, 2006).map(|error| error.name),
+            Some("ConstraintSeeds".into())
+        );
+    }
+
+    #[test]
+    fn parse_error_enum_anchor_error_code_offset() {
+        let source = r#"
+#[error_code(offset = 7000)]
+pub enum VaultError {
+    Locked,
+    Unauthorized = 5,
+}
+"#;
+
+        let errors = parse_error_enum(source).unwrap().errors;
+        assert_eq!(errors[&7000].name, "Locked");
+        assert_eq!(errors[&7005].name, "Unauthorized");
+    }
+}
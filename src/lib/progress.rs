@@ -3,10 +3,10 @@
 use std::future::Future;
 
 use console::Emoji;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use tokio::time::Duration;
 
-use crate::constants::emoji;
+use crate::{constants::emoji, output::OutputFormat};
 
 /// Terminal progress utility struct.
 #[derive(Default)]
@@ -55,6 +55,7 @@ impl<'a> Progress<'a> {
         R: Future<Output = miette::Result<O>>,
     {
         let pb = ProgressBar::new_spinner();
+        hide_if_not_human(&pb);
         pb.set_style(ProgressStyle::with_template(" {spinner:.green} {msg}").unwrap());
         pb.enable_steady_tick(Duration::from_millis(120));
 
@@ -89,6 +90,7 @@ impl<'a> Progress<'a> {
         let len = vec.len();
         let width = len.to_string().len();
         let pb = ProgressBar::new(len as u64);
+        hide_if_not_human(&pb);
         pb.set_style(
             ProgressStyle::with_template(&format!(
                 "[{{pos:>{width}}}/{{len:{width}}}] {{bar:.blue/white}} {{msg}}"
@@ -127,3 +129,10 @@ fn handle_output(pb: &ProgressBar, msg: Option<&str>, color: &str, prefix: Emoji
         pb.set_message(msg.to_owned());
     }
 }
+
+/// Hide the progress bar if the output format is not [`OutputFormat::Human`].
+fn hide_if_not_human(pb: &ProgressBar) {
+    if !OutputFormat::is_human() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
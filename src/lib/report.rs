@@ -0,0 +1,256 @@
+//! ELF size and compute unit reporting.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    framework::Framework, framework_utils::ProgramName, hook::HookEvent, output::print_info,
+};
+
+lazy_static! {
+    /// Last known ELF sizes by the ELF paths.
+    static ref ELF_SIZES: Mutex<HashMap<PathBuf, u64>> = Mutex::new(HashMap::new());
+    /// Consumed compute units by the program and the instruction names.
+    static ref COMPUTE_UNITS: Mutex<HashMap<(String, String), ComputeUnits>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Instruction name that is used when the program doesn't log the instruction name.
+const UNKNOWN_INSTRUCTION: &str = "unknown";
+
+/// Consumed compute units of an instruction.
+#[derive(Default)]
+struct ComputeUnits {
+    /// Consumed compute units since the last deployment
+    current: Option<u64>,
+    /// Consumed compute units before the last deployment
+    previous: Option<u64>,
+}
+
+/// Remember the sizes of the existing ELFs of the programs inside the given directory.
+///
+/// This should be called before the build to compare the sizes with the ELFs of the previous
+/// session.
+pub async fn snapshot_elf_sizes<F: Framework + ?Sized>(framework: &F, program_path: &Path) {
+    let mut elf_sizes = ELF_SIZES.lock().await;
+    for (_, elf_path) in framework.get_elf_paths(program_path).await {
+        if elf_sizes.contains_key(&elf_path) {
+            continue;
+        }
+        if let Ok(metadata) = fs::metadata(&elf_path).await {
+            elf_sizes.insert(elf_path, metadata.len());
+        }
+    }
+}
+
+/// Report the ELF sizes of the programs inside the given directory and warn about the crossed
+/// size thresholds.
+pub async fn report_elf_sizes<F: Framework + ?Sized>(
+    framework: &F,
+    program_path: &Path,
+) -> miette::Result<()> {
+    for (program, elf_path) in framework.get_elf_paths(program_path).await {
+        let Ok(metadata) = fs::metadata(&elf_path).await else {
+            continue;
+        };
+        let size = metadata.len();
+        let previous_size = ELF_SIZES.lock().await.insert(elf_path.clone(), size);
+
+        let elf_file_name = ProgramName::new(&program).elf_file_name();
+        match previous_size {
+            Some(previous_size) => print_info(format!(
+                "{elf_file_name}: {} ({})",
+                format_size(size),
+                format_size_delta(size, previous_size)
+            )),
+            None => print_info(format!("{elf_file_name}: {}", format_size(size))),
+        }
+
+        for threshold in &framework.config().report.elf_size_thresholds {
+            if size > *threshold && previous_size.map_or(true, |previous| previous <= *threshold) {
+                framework
+                    .warn(format!(
+                        "{elf_file_name} has grown past {threshold} bytes: {size} bytes"
+                    ))
+                    .await?;
+            }
+        }
+
+        framework
+            .emit(HookEvent::ElfSize {
+                program,
+                elf_path,
+                size,
+                previous_size,
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Start a new compute unit measurement of the program, which means the current measurements
+/// become the previous ones.
+///
+/// This should be called after each deployment.
+pub async fn reset_compute_units(program: &ProgramName) {
+    let program = program.snake_case();
+    for ((name, _), compute_units) in COMPUTE_UNITS.lock().await.iter_mut() {
+        if *name == program && compute_units.current.is_some() {
+            compute_units.previous = compute_units.current.take();
+        }
+    }
+}
+
+/// Report the consumed compute units of the program's instructions in the transaction logs.
+pub async fn report_compute_units<F: Framework + ?Sized>(
+    framework: &F,
+    program: &str,
+    program_id: &str,
+    logs: &[String],
+) -> miette::Result<()> {
+    for (instruction, consumed) in parse_compute_units(program_id, logs) {
+        let previous = {
+            let mut compute_units = COMPUTE_UNITS.lock().await;
+            let entry = compute_units
+                .entry((ProgramName::new(program).snake_case(), instruction.clone()))
+                .or_default();
+            entry.current = Some(consumed);
+            entry.previous
+        };
+
+        match previous {
+            Some(previous) => print_info(format!(
+                "{program} {instruction}: {consumed} CU ({:+} since the last deployment)",
+                consumed as i64 - previous as i64
+            )),
+            None => print_info(format!("{program} {instruction}: {consumed} CU")),
+        }
+
+        framework
+            .emit(HookEvent::ComputeUnits {
+                program: program.to_owned(),
+                instruction,
+                consumed,
+                previous,
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Parse the consumed compute units of the program's instructions from the transaction logs.
+///
+/// Instruction names are only available for programs that log `Instruction: <name>`, e.g. Anchor
+/// programs.
+fn parse_compute_units(program_id: &str, logs: &[String]) -> Vec<(String, u64)> {
+    lazy_static! {
+        static ref INVOKE_REGEX: Regex = Regex::new(r"^Program (\w+) invoke \[\d+\]").unwrap();
+        static ref CONSUMED_REGEX: Regex =
+            Regex::new(r"^Program (\w+) consumed (\d+) of \d+ compute units").unwrap();
+        static ref RESULT_REGEX: Regex = Regex::new(r"^Program \w+ (success|failed)").unwrap();
+    }
+
+    // Stack of the invoked program ids and their instruction names
+    let mut invocations: Vec<(&str, Option<&str>)> = vec![];
+    let mut compute_units = vec![];
+    for log in logs {
+        if let Some(captures) = INVOKE_REGEX.captures(log) {
+            invocations.push((captures.get(1).unwrap().as_str(), None));
+        } else if let Some(name) = log.strip_prefix("Program log: Instruction: ") {
+            if let Some((_, instruction)) = invocations.last_mut() {
+                *instruction = Some(name.trim());
+            }
+        } else if let Some(captures) = CONSUMED_REGEX.captures(log) {
+            if &captures[1] != program_id {
+                continue;
+            }
+            if let Ok(consumed) = captures[2].parse() {
+                let instruction = invocations
+                    .last()
+                    .and_then(|(_, instruction)| *instruction)
+                    .unwrap_or(UNKNOWN_INSTRUCTION);
+                compute_units.push((instruction.to_owned(), consumed));
+            }
+        } else if RESULT_REGEX.is_match(log) {
+            invocations.pop();
+        }
+    }
+
+    compute_units
+}
+
+/// Format the size in bytes as KiB, e.g `180.2 KiB`.
+pub fn format_size(size: u64) -> String {
+    format!("{:.1} KiB", size as f64 / 1024.0)
+}
+
+/// Format the difference of the sizes as KiB, e.g `+1.3 KiB`.
+fn format_size_delta(size: u64, previous_size: u64) -> String {
+    match size as i64 - previous_size as i64 {
+        0 => "no change".into(),
+        delta => format!("{:+.1} KiB", delta as f64 / 1024.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+    fn to_logs(logs: &str) -> Vec<String> {
+        logs.lines().map(ToOwned::to_owned).collect()
+    }
+
+    #[test]
+    fn parse_compute_units_with_cpi() {
+        let logs = to_logs(
+            "\
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]
+Program log: Instruction: Deposit
+Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]
+Program log: Instruction: Transfer
+Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4645 of 183422 compute units
+Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 21223 of 200000 compute units
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success",
+        );
+
+        assert_eq!(
+            parse_compute_units(PROGRAM_ID, &logs),
+            vec![("Deposit".to_owned(), 21223)]
+        );
+    }
+
+    #[test]
+    fn parse_compute_units_without_instruction_name() {
+        let logs = to_logs(
+            "\
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]
+Program log: Counter: 1
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 1337 of 200000 compute units
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]
+Program log: Instruction: Increment
+Program log: AnchorError thrown in programs/counter/src/lib.rs:27. Error Code: Overflow. Error Number: 6000. Error Message: Counter overflowed.
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS consumed 2281 of 198663 compute units
+Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS failed: custom program error: 0x1770",
+        );
+
+        assert_eq!(
+            parse_compute_units(PROGRAM_ID, &logs),
+            vec![
+                (UNKNOWN_INSTRUCTION.to_owned(), 1337),
+                ("Increment".to_owned(), 2281)
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Critical warnings of the SBF build output, e.g. `cargo build-sbf`.
+
+use lazy_static::lazy_static;
+use miette::Diagnostic;
+use regex::Regex;
+use thiserror::Error;
+
+/// Function's stack frame exceeds the maximum stack offset.
+///
+/// This doesn't fail the build but exceeding the maximum stack offset may cause undefined behavior
+/// during execution.
+#[derive(Error, Diagnostic, Debug, PartialEq, Eq)]
+#[error(
+    "Stack offset of {offset} exceeded max offset of {max_offset} by {exceeded} bytes in \
+    `{function}`"
+)]
+#[diagnostic(
+    severity(Warning),
+    help(
+        "Minimize large stack variables, e.g. move them to the heap with `Box`. Exceeding the \
+        maximum stack offset may cause undefined behavior during execution."
+    )
+)]
+pub struct StackWarning {
+    /// Demangled name of the function
+    pub function: String,
+    /// Stack offset of the function
+    pub offset: u64,
+    /// Maximum stack offset
+    pub max_offset: u64,
+    /// Amount of bytes that exceeded the maximum stack offset
+    pub exceeded: u64,
+}
+
+/// Parse the stack offset warnings from the build output.
+///
+/// Warnings are deduplicated by their function names.
+pub fn parse_stack_warnings<S: AsRef<str>>(output: S) -> Vec<StackWarning> {
+    lazy_static! {
+        static ref STACK_OFFSET_REGEX: Regex = Regex::new(
+            r"Function (\S+) Stack offset of (\d+) exceeded max offset of (\d+) by (\d+) bytes"
+        )
+        .unwrap();
+    }
+
+    let output = console::strip_ansi_codes(output.as_ref());
+    let mut warnings: Vec<StackWarning> = vec![];
+    for captures in STACK_OFFSET_REGEX.captures_iter(&output) {
+        let function = format!("{:#}", rustc_demangle::demangle(&captures[1]));
+        if warnings.iter().any(|warning| warning.function == function) {
+            continue;
+        }
+
+        let parse = |index: usize| captures[index].parse().unwrap_or_default();
+        warnings.push(StackWarning {
+            function,
+            offset: parse(2),
+            max_offset: parse(3),
+            exceeded: parse(4),
+        });
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stack_warnings_demangles_and_dedupes() {
+        let output = "\
+[2023-05-10T09:12:31.456789000Z ERROR cargo_build_sbf] Error: Function _ZN112_$LT$solana_program..instruction..InstructionError$u20$as$u20$solana_frozen_abi..abi_example..AbiEnumVisitor$GT$14visit_enum_abi17h0eaf9bd3f5e6e1b0E Stack offset of 4584 exceeded max offset of 4096 by 488 bytes, please minimize large stack variables
+[2023-05-10T09:12:31.456823000Z ERROR cargo_build_sbf] Error: Function _ZN112_$LT$solana_program..instruction..InstructionError$u20$as$u20$solana_frozen_abi..abi_example..AbiEnumVisitor$GT$14visit_enum_abi17h0eaf9bd3f5e6e1b0E Stack offset of 4584 exceeded max offset of 4096 by 488 bytes, please minimize large stack variables
+\x1b[1m\x1b[31mError\x1b[0m: Function _ZN7counter9processor9increment17h9c8a5f3e2d1b0a47E Stack offset of 4160 exceeded max offset of 4096 by 64 bytes, please minimize large stack variables
+    Finished release [optimized] target(s) in 12.34s
+";
+
+        let warnings = parse_stack_warnings(output);
+        assert_eq!(
+            warnings,
+            vec![
+                StackWarning {
+                    function: "<solana_program::instruction::InstructionError as \
+                        solana_frozen_abi::abi_example::AbiEnumVisitor>::visit_enum_abi"
+                        .into(),
+                    offset: 4584,
+                    max_offset: 4096,
+                    exceeded: 488,
+                },
+                StackWarning {
+                    function: "counter::processor::increment".into(),
+                    offset: 4160,
+                    max_offset: 4096,
+                    exceeded: 64,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_stack_warnings_without_warnings() {
+        let output = "\
+   Compiling counter v0.1.0 (/home/user/counter)
+    Finished release [optimized] target(s) in 3.21s
+";
+
+        assert!(parse_stack_warnings(output).is_empty());
+    }
+}
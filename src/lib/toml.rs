@@ -1,9 +1,10 @@
 //! TOML related methods.
 
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 
 use cargo_toml::Manifest;
 use miette::IntoDiagnostic;
+use serde::Deserialize;
 use tokio::fs;
 
 use crate::constants::filename;
@@ -17,3 +18,73 @@ pub async fn read_cargo_toml<P: AsRef<Path>>(origin: P) -> miette::Result<Manife
     )
     .into_diagnostic()
 }
+
+/// Subset of `Anchor.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AnchorToml {
+    /// Workspace configuration.
+    pub workspace: Option<AnchorWorkspace>,
+    /// Provider configuration.
+    pub provider: AnchorProvider,
+    /// Programs by the cluster names and the program names.
+    pub programs: HashMap<String, HashMap<String, toml::Value>>,
+    /// Toolchain versions.
+    pub toolchain: AnchorToolchain,
+    /// Scripts by their names.
+    pub scripts: HashMap<String, String>,
+}
+
+impl AnchorToml {
+    /// Get the programs of the `[provider]` cluster by their names.
+    ///
+    /// `localnet` is used if the cluster doesn't have a `[programs]` table, e.g. it's a URL.
+    pub fn get_cluster_programs(&self) -> Option<&HashMap<String, toml::Value>> {
+        self.provider
+            .cluster
+            .as_ref()
+            .and_then(|cluster| self.programs.get(cluster))
+            .or_else(|| self.programs.get(LOCALNET))
+    }
+}
+
+/// Name of the local cluster in `Anchor.toml`.
+const LOCALNET: &str = "localnet";
+
+/// `[provider]` table of `Anchor.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AnchorProvider {
+    /// Cluster name or URL
+    pub cluster: Option<String>,
+}
+
+/// `[workspace]` table of `Anchor.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AnchorWorkspace {
+    /// Program paths, defaults to `programs/*`
+    pub members: Vec<String>,
+    /// Excluded program paths
+    pub exclude: Vec<String>,
+}
+
+/// `[toolchain]` table of `Anchor.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AnchorToolchain {
+    /// Anchor CLI version
+    pub anchor_version: Option<String>,
+    /// Solana CLI version
+    pub solana_version: Option<String>,
+}
+
+/// Reads and parses the `Anchor.toml` at the given project directory.
+pub async fn read_anchor_toml<P: AsRef<Path>>(origin: P) -> miette::Result<AnchorToml> {
+    toml::from_str::<AnchorToml>(
+        &fs::read_to_string(origin.as_ref().join(filename::ANCHOR_TOML))
+            .await
+            .into_diagnostic()?,
+    )
+    .into_diagnostic()
+}
@@ -0,0 +1,825 @@
+//! Full-screen dashboard of [`OutputFormat::Tui`](crate::output::OutputFormat::Tui).
+//!
+//! The dashboard is fed by the [`HookEvent`]s, which means it shows the same information as the
+//! other output formats.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor::Show,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use miette::IntoDiagnostic;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use tokio::{
+    fs,
+    sync::mpsc::{self, UnboundedSender},
+    time,
+};
+
+use crate::{
+    command::WCommand,
+    control::{self, ControlRequest, ControlResponse},
+    fixtures,
+    framework::WatchableFramework,
+    framework_utils::{get_pubkey_from_keypair_path, restart_test_validator, ProgramName},
+    hook::HookEvent,
+    logs,
+    report::format_size,
+};
+
+/// Help line that lists the available keys.
+const HELP: &str = "r rebuild all | d redeploy all | p pause/resume | v restart validator with \
+reset | l toggle program logs | c clear logs | ↑↓ PgUp PgDn scroll logs | q quit";
+
+/// Maximum amount of log lines to keep.
+const MAX_LOG_LINES: usize = 5000;
+
+/// Interval of checking the test validator status.
+const VALIDATOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Colors of the program names in the program logs, same as the human output format.
+const PROGRAM_COLORS: [Color; 5] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+];
+
+/// Message that updates the dashboard.
+enum Message {
+    /// Watch event.
+    Event(HookEvent),
+    /// Key press.
+    Key(KeyEvent),
+    /// Slot the program was last deployed in.
+    DeploySlot {
+        /// Program name
+        name: String,
+        /// Deploy slot
+        slot: u64,
+    },
+    /// Test validator status.
+    Validator(ValidatorStatus),
+}
+
+/// Command to run in the background.
+enum Command {
+    /// Dispatch the request as if it was sent to the control server.
+    Request(ControlRequest),
+    /// Restart the test validator with reset and redeploy all programs.
+    RestartValidator,
+}
+
+/// Status of the test validator.
+#[derive(Default)]
+enum ValidatorStatus {
+    /// Status is not checked yet.
+    #[default]
+    Unknown,
+    /// Validator is running.
+    Running {
+        /// Current slot
+        slot: u64,
+    },
+    /// Validator is not reachable.
+    NotRunning,
+}
+
+/// Status of the last build or deployment.
+enum RunStatus {
+    /// Still running.
+    Running,
+    /// Finished running.
+    Finished {
+        /// Whether it was successful
+        success: bool,
+        /// How long it took
+        duration: Duration,
+        /// When it finished
+        at: Instant,
+    },
+}
+
+/// Row of the program table.
+#[derive(Default)]
+struct ProgramRow {
+    /// Root directory of the program
+    path: PathBuf,
+    /// Program id, only available if the program keypair exists
+    program_id: Option<String>,
+    /// Last build status
+    build: Option<RunStatus>,
+    /// Size of the program ELF in bytes
+    elf_size: Option<u64>,
+    /// Last deployment status
+    deploy: Option<RunStatus>,
+    /// Slot of the last deployment
+    deploy_slot: Option<u64>,
+    /// Transaction signature of the last deployment
+    signature: Option<String>,
+}
+
+/// State of the dashboard.
+#[derive(Default)]
+struct Dashboard {
+    /// Programs by their names
+    programs: BTreeMap<String, ProgramRow>,
+    /// Test validator status
+    validator: ValidatorStatus,
+    /// Log lines
+    logs: Vec<Line<'static>>,
+    /// Amount of lines the logs are scrolled up from the bottom
+    scroll: usize,
+}
+
+/// Restores the terminal when dropped.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Switch to the alternate screen in raw mode.
+    fn enter() -> miette::Result<Self> {
+        enable_raw_mode().into_diagnostic()?;
+        execute!(io::stdout(), EnterAlternateScreen).into_diagnostic()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+    }
+}
+
+/// Run the dashboard until the user quits.
+///
+/// This should be called before the framework is initialized in order to not miss the events.
+pub async fn run(framework: Arc<dyn WatchableFramework>) -> miette::Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    framework
+        .hooks()
+        .subscribe_all({
+            let tx = tx.clone();
+            move |event| {
+                let _ = tx.send(Message::Event(event.clone()));
+                Ok(())
+            }
+        })
+        .await;
+
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout())).into_diagnostic()?;
+
+    // Reading the terminal events blocks, using a thread instead of a blocking task to not block
+    // the runtime from shutting down
+    thread::spawn({
+        let tx = tx.clone();
+        move || {
+            while let Ok(event) = event::read() {
+                if let Event::Key(key) = event {
+                    if key.kind == KeyEventKind::Press && tx.send(Message::Key(key)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    tokio::spawn(poll_validator(tx.clone()));
+    let commands = spawn_command_worker(framework.clone());
+
+    let mut dashboard = Dashboard::default();
+    let mut tick = time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            Some(message) = rx.recv() => match message {
+                Message::Event(event) => dashboard.on_event(framework.as_ref(), &tx, event).await,
+                Message::Key(key) => {
+                    if !dashboard.on_key(framework.clone(), key, &commands).await {
+                        return Ok(());
+                    }
+                }
+                Message::DeploySlot { name, slot } => {
+                    if let Some(row) = dashboard.programs.get_mut(&name) {
+                        row.deploy_slot = Some(slot);
+                    }
+                }
+                Message::Validator(status) => dashboard.validator = status,
+            },
+            _ = tick.tick() => dashboard.sync_programs(framework.as_ref()).await,
+        }
+
+        terminal
+            .draw(|frame| dashboard.render(frame))
+            .into_diagnostic()?;
+    }
+}
+
+/// Spawn a task that runs the commands one by one, the same way they would run from the keyboard
+/// in the other output formats.
+fn spawn_command_worker(framework: Arc<dyn WatchableFramework>) -> UnboundedSender<Command> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(command) = rx.recv().await {
+            let result = match command {
+                Command::Request(request) => request_ok(framework.as_ref(), request).await,
+                Command::RestartValidator => restart_validator(framework.as_ref()).await,
+            };
+
+            if let Err(err) = result {
+                let _ = framework
+                    .emit(HookEvent::Error {
+                        message: err.to_string(),
+                    })
+                    .await;
+            }
+        }
+    });
+
+    tx
+}
+
+/// Dispatch the request and convert the error response to an error.
+async fn request_ok(
+    framework: &dyn WatchableFramework,
+    request: ControlRequest,
+) -> miette::Result<()> {
+    match control::dispatch(framework, request).await {
+        ControlResponse::Error { message } => Err(miette::miette!(message)),
+        _ => Ok(()),
+    }
+}
+
+/// Restart the test validator with reset and redeploy all programs.
+async fn restart_validator(framework: &dyn WatchableFramework) -> miette::Result<()> {
+    restart_test_validator(framework.origin(), framework.config()).await?;
+    request_ok(framework, ControlRequest::Deploy { program: None }).await?;
+    fixtures::seed(framework).await
+}
+
+/// Check the test validator status periodically with `solana slot`.
+async fn poll_validator(tx: UnboundedSender<Message>) {
+    let mut interval = time::interval(VALIDATOR_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let status = match WCommand::new("solana slot").output().await {
+            Ok(output) if output.status().success() => match output.stdout().trim().parse() {
+                Ok(slot) => ValidatorStatus::Running { slot },
+                Err(_) => ValidatorStatus::NotRunning,
+            },
+            _ => ValidatorStatus::NotRunning,
+        };
+
+        if tx.send(Message::Validator(status)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Get the slot the program was last deployed in with `solana program show`.
+async fn get_deploy_slot(program_id: &str) -> Option<u64> {
+    let output = WCommand::new(format!("solana program show {program_id} --output json"))
+        .output()
+        .await
+        .ok()?;
+    let value = serde_json::from_str::<serde_json::Value>(output.stdout()).ok()?;
+    value.get("lastDeploySlot")?.as_u64()
+}
+
+impl Dashboard {
+    /// Add the new programs and remove the stale ones.
+    async fn sync_programs(&mut self, framework: &dyn WatchableFramework) {
+        let programs = framework.project_map().get_all().await;
+        self.programs.retain(|name, _| programs.contains_key(name));
+        for (name, path) in programs {
+            if let Entry::Vacant(entry) = self.programs.entry(name) {
+                let mut row = ProgramRow {
+                    path,
+                    ..Default::default()
+                };
+                row.refresh(framework, entry.key()).await;
+                entry.insert(row);
+            }
+        }
+    }
+
+    /// Update the dashboard based on the event.
+    async fn on_event(
+        &mut self,
+        framework: &dyn WatchableFramework,
+        tx: &UnboundedSender<Message>,
+        event: HookEvent,
+    ) {
+        let origin = framework.origin();
+        match event {
+            HookEvent::ChangeDetected { paths } => {
+                let paths = paths
+                    .iter()
+                    .map(|path| display_path(origin, path))
+                    .collect::<Vec<_>>();
+                self.log(
+                    format!("Change detected: {}", paths.join(", ")),
+                    Color::Blue,
+                );
+            }
+            HookEvent::BeforeBuild { program_path } => {
+                for (_, row) in self.get_rows_in(&program_path) {
+                    row.build = Some(RunStatus::Running);
+                }
+                self.log(
+                    format!("Building {}...", display_path(origin, &program_path)),
+                    Color::Reset,
+                );
+            }
+            HookEvent::AfterBuild {
+                program_path,
+                success,
+                duration,
+                output,
+            } => {
+                for (name, row) in self.get_rows_in(&program_path) {
+                    row.build = Some(RunStatus::finished(success, duration));
+                    row.refresh(framework, name).await;
+                }
+                self.log_run(
+                    "Build",
+                    success,
+                    &display_path(origin, &program_path),
+                    duration,
+                );
+                self.log_output(output);
+            }
+            HookEvent::BeforeDeploy { elf_path } => {
+                if let Some((_, row)) = self.get_row_of_elf(&elf_path) {
+                    row.deploy = Some(RunStatus::Running);
+                }
+                self.log(
+                    format!("Deploying {}...", display_path(origin, &elf_path)),
+                    Color::Reset,
+                );
+            }
+            HookEvent::AfterDeploy {
+                elf_path,
+                success,
+                duration,
+                program_id,
+                signature,
+                output,
+            } => {
+                if let Some((name, row)) = self.get_row_of_elf(&elf_path) {
+                    row.deploy = Some(RunStatus::finished(success, duration));
+                    if success {
+                        row.deploy_slot = None;
+                        row.signature = signature;
+                        if let Some(program_id) = program_id {
+                            row.program_id = Some(program_id);
+                        }
+                        if let Some(program_id) = row.program_id.clone() {
+                            let name = name.to_owned();
+                            let tx = tx.clone();
+                            tokio::spawn(async move {
+                                if let Some(slot) = get_deploy_slot(&program_id).await {
+                                    let _ = tx.send(Message::DeploySlot { name, slot });
+                                }
+                            });
+                        }
+                    }
+                }
+                self.log_run(
+                    "Deployment",
+                    success,
+                    &display_path(origin, &elf_path),
+                    duration,
+                );
+                self.log_output(output);
+            }
+            HookEvent::ProgramIdUpdated {
+                program_path,
+                program_id,
+            } => {
+                for (_, row) in self.get_rows_in(&program_path) {
+                    row.program_id = Some(program_id.clone());
+                }
+                self.log(
+                    format!(
+                        "Updated the program id of {} to {program_id}",
+                        display_path(origin, &program_path)
+                    ),
+                    Color::Blue,
+                );
+            }
+            HookEvent::ProgramLog {
+                program,
+                signature,
+                success,
+                logs,
+                ..
+            } => {
+                let index = self
+                    .programs
+                    .keys()
+                    .position(|name| *name == program)
+                    .unwrap_or_default();
+                let prefix = Span::styled(
+                    format!("[{program}] "),
+                    Style::default()
+                        .fg(PROGRAM_COLORS[index % PROGRAM_COLORS.len()])
+                        .add_modifier(Modifier::BOLD),
+                );
+                let mut spans = vec![
+                    prefix.clone(),
+                    Span::styled(signature, Style::default().add_modifier(Modifier::DIM)),
+                ];
+                if !success {
+                    spans.push(Span::styled(" failed", Style::default().fg(Color::Red)));
+                }
+                self.push_log(Line::from(spans));
+                for log in logs {
+                    self.push_log(Line::from(vec![prefix.clone(), Span::raw(log)]));
+                }
+            }
+            HookEvent::IdlChanged { program, .. } => {
+                self.log(format!("IDL of {program} has changed"), Color::Blue)
+            }
+            HookEvent::ElfSize {
+                program,
+                size,
+                previous_size,
+                ..
+            } => {
+                if let Some(row) = self.programs.get_mut(&program) {
+                    row.elf_size = Some(size);
+                }
+                let delta = match previous_size {
+                    Some(previous_size) if previous_size != size => format!(
+                        " ({:+.1} KiB)",
+                        (size as i64 - previous_size as i64) as f64 / 1024.0
+                    ),
+                    _ => String::new(),
+                };
+                self.log(
+                    format!("ELF size of {program}: {}{delta}", format_size(size)),
+                    Color::Reset,
+                );
+            }
+            HookEvent::ComputeUnits {
+                program,
+                instruction,
+                consumed,
+                previous,
+            } => {
+                let delta = previous
+                    .map(|previous| {
+                        format!(
+                            " ({:+} since the last deployment)",
+                            consumed as i64 - previous as i64
+                        )
+                    })
+                    .unwrap_or_default();
+                self.log(
+                    format!("{program} {instruction}: {consumed} CU{delta}"),
+                    Color::Reset,
+                );
+            }
+            HookEvent::Warning { message } => self.log(message, Color::Yellow),
+            HookEvent::Error { message } => self.log(message, Color::Red),
+        }
+    }
+
+    /// Handle the key press.
+    ///
+    /// Returns `false` if the user wants to quit.
+    async fn on_key(
+        &mut self,
+        framework: Arc<dyn WatchableFramework>,
+        key: KeyEvent,
+        commands: &UnboundedSender<Command>,
+    ) -> bool {
+        let request = |request| {
+            let _ = commands.send(Command::Request(request));
+        };
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('r') => request(ControlRequest::Build { program: None }),
+            KeyCode::Char('d') => request(ControlRequest::Deploy { program: None }),
+            KeyCode::Char('p') => {
+                let paused = !control::is_paused();
+                control::set_paused(paused);
+                match paused {
+                    true => self.log("Paused watching", Color::Blue),
+                    false => self.log("Resumed watching", Color::Blue),
+                }
+            }
+            KeyCode::Char('l') => match logs::toggle(framework).await {
+                true => self.log("Started program logs", Color::Blue),
+                false => self.log("Stopped program logs", Color::Blue),
+            },
+            KeyCode::Char('v') => {
+                self.log("Restarting Solana test validator...", Color::Reset);
+                let _ = commands.send(Command::RestartValidator);
+            }
+            KeyCode::Char('c') => {
+                self.logs.clear();
+                self.scroll = 0;
+            }
+            KeyCode::Up => self.scroll_by(1),
+            KeyCode::Down => self.scroll_by(-1),
+            KeyCode::PageUp => self.scroll_by(10),
+            KeyCode::PageDown => self.scroll_by(-10),
+            KeyCode::Home => self.scroll = self.logs.len(),
+            KeyCode::End => self.scroll = 0,
+            _ => (),
+        }
+
+        true
+    }
+
+    /// Scroll the logs up by the given amount of lines, negative amount scrolls down.
+    fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines)
+            .min(self.logs.len().saturating_sub(1));
+    }
+
+    /// Get the rows of the programs that are inside the given directory.
+    fn get_rows_in<'a>(
+        &'a mut self,
+        path: &'a Path,
+    ) -> impl Iterator<Item = (&'a String, &'a mut ProgramRow)> {
+        self.programs
+            .iter_mut()
+            .filter(move |(_, row)| row.path.starts_with(path))
+    }
+
+    /// Get the row of the program that has the given ELF.
+    fn get_row_of_elf(&mut self, elf_path: &Path) -> Option<(&String, &mut ProgramRow)> {
+        let elf_file_name = elf_path.file_name()?.to_str()?;
+        self.programs
+            .iter_mut()
+            .find(|(name, _)| ProgramName::new(name.as_str()).elf_file_name() == elf_file_name)
+    }
+
+    /// Add a log line with the given color.
+    fn log<S: Into<String>>(&mut self, line: S, color: Color) {
+        self.push_log(Line::from(Span::styled(
+            line.into(),
+            Style::default().fg(color),
+        )));
+    }
+
+    /// Add the result of a build or deployment to the logs.
+    fn log_run(&mut self, name: &str, success: bool, target: &str, duration: Duration) {
+        match success {
+            true => self.log(
+                format!("{name} succeeded: {target} ({})", format_duration(duration)),
+                Color::Green,
+            ),
+            false => self.log(format!("{name} failed: {target}"), Color::Red),
+        }
+    }
+
+    /// Add the captured command output to the logs.
+    fn log_output(&mut self, output: Option<String>) {
+        for line in output.iter().flat_map(|output| output.lines()) {
+            self.push_log(Line::from(Span::styled(
+                line.to_owned(),
+                Style::default().add_modifier(Modifier::DIM),
+            )));
+        }
+    }
+
+    /// Add the line to the logs, keeping the scroll position if the logs are scrolled up.
+    fn push_log(&mut self, line: Line<'static>) {
+        self.logs.push(line);
+        if self.scroll != 0 {
+            self.scroll += 1;
+        }
+        if self.logs.len() > MAX_LOG_LINES {
+            self.logs.remove(0);
+        }
+    }
+
+    /// Draw the dashboard.
+    fn render<B: Backend>(&self, frame: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(self.programs.len() as u16 + 3),
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        self.render_programs(frame, chunks[0]);
+        self.render_status(frame, chunks[1]);
+        self.render_logs(frame, chunks[2]);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+            chunks[3],
+        );
+    }
+
+    /// Draw the program table.
+    fn render_programs<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let header = Row::new([
+            "Name",
+            "Program id",
+            "Last build",
+            "ELF size",
+            "Last deploy",
+            "Deploy slot",
+            "Signature",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = self.programs.iter().map(|(name, row)| {
+            Row::new([
+                Cell::from(name.as_str()),
+                Cell::from(row.program_id.as_deref().unwrap_or("-")),
+                RunStatus::cell(&row.build),
+                Cell::from(row.elf_size.map(format_size).unwrap_or_else(|| "-".into())),
+                RunStatus::cell(&row.deploy),
+                Cell::from(
+                    row.deploy_slot
+                        .map(|slot| slot.to_string())
+                        .unwrap_or_else(|| "-".into()),
+                ),
+                Cell::from(
+                    row.signature
+                        .as_deref()
+                        .map(shorten)
+                        .unwrap_or_else(|| "-".into()),
+                ),
+            ])
+        });
+
+        let widths = [
+            Constraint::Min(12),
+            Constraint::Length(44),
+            Constraint::Length(20),
+            Constraint::Length(10),
+            Constraint::Length(20),
+            Constraint::Length(11),
+            Constraint::Length(19),
+        ];
+        let table = Table::new(rows)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(" Programs "))
+            .widths(&widths)
+            .column_spacing(2);
+        frame.render_widget(table, area);
+    }
+
+    /// Draw the validator and the watch status.
+    fn render_status<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let validator = match self.validator {
+            ValidatorStatus::Unknown => Span::raw("checking..."),
+            ValidatorStatus::Running { slot } => Span::styled(
+                format!("running, slot {slot}"),
+                Style::default().fg(Color::Green),
+            ),
+            ValidatorStatus::NotRunning => {
+                Span::styled("not running", Style::default().fg(Color::Red))
+            }
+        };
+        let watching = match control::is_paused() {
+            true => Span::styled("paused", Style::default().fg(Color::Yellow)),
+            false => Span::styled("active", Style::default().fg(Color::Green)),
+        };
+
+        let line = Line::from(vec![
+            Span::raw("Validator: "),
+            validator,
+            Span::raw("    Watching: "),
+            watching,
+        ]);
+        frame.render_widget(
+            Paragraph::new(line).block(Block::default().borders(Borders::ALL).title(" Status ")),
+            area,
+        );
+    }
+
+    /// Draw the visible part of the logs.
+    fn render_logs<B: Backend>(&self, frame: &mut Frame<B>, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let end = self.logs.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+        let title = match self.scroll {
+            0 => " Logs ".into(),
+            scroll => format!(" Logs (scrolled up {scroll} lines) "),
+        };
+
+        frame.render_widget(
+            Paragraph::new(self.logs[start..end].to_vec())
+                .block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+}
+
+impl ProgramRow {
+    /// Read the program id and the ELF size from the deploy directory.
+    async fn refresh(&mut self, framework: &dyn WatchableFramework, name: &str) {
+        let program_name = ProgramName::new(name);
+        let keypair_path = framework
+            .deploy_path()
+            .join(program_name.keypair_file_name());
+        if self.program_id.is_none() && keypair_path.exists() {
+            self.program_id = get_pubkey_from_keypair_path(keypair_path).await.ok();
+        }
+
+        let elf_path = framework.deploy_path().join(program_name.elf_file_name());
+        self.elf_size = fs::metadata(elf_path)
+            .await
+            .ok()
+            .map(|metadata| metadata.len());
+    }
+}
+
+impl RunStatus {
+    /// Create a finished status.
+    fn finished(success: bool, duration: Duration) -> Self {
+        Self::Finished {
+            success,
+            duration,
+            at: Instant::now(),
+        }
+    }
+
+    /// Get the table cell of the status.
+    fn cell(status: &Option<Self>) -> Cell<'static> {
+        match status {
+            None => Cell::from("-"),
+            Some(Self::Running) => {
+                Cell::from("running...").style(Style::default().fg(Color::Yellow))
+            }
+            Some(Self::Finished {
+                success,
+                duration,
+                at,
+            }) => {
+                let (text, color) = match success {
+                    true => ("ok", Color::Green),
+                    false => ("failed", Color::Red),
+                };
+                Cell::from(format!(
+                    "{text} {}, {} ago",
+                    format_duration(*duration),
+                    format_elapsed(at.elapsed())
+                ))
+                .style(Style::default().fg(color))
+            }
+        }
+    }
+}
+
+/// Display the path relative to the origin.
+fn display_path(origin: &Path, path: &Path) -> String {
+    match path.strip_prefix(origin) {
+        Ok(relative_path) if relative_path.as_os_str().is_empty() => ".".into(),
+        Ok(relative_path) => relative_path.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Format the duration with one decimal, e.g `3.2s`.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+/// Format the elapsed time with the largest unit, e.g `5m`.
+fn format_elapsed(elapsed: Duration) -> String {
+    match elapsed.as_secs() {
+        secs @ 0..=59 => format!("{secs}s"),
+        secs @ 60..=3599 => format!("{}m", secs / 60),
+        secs => format!("{}h", secs / 3600),
+    }
+}
+
+/// Shorten the signature to its first and last characters.
+fn shorten(signature: &str) -> String {
+    match signature.len() {
+        0..=16 => signature.to_owned(),
+        len => format!("{}..{}", &signature[..8], &signature[len - 8..]),
+    }
+}
@@ -1,44 +1,144 @@
 //! Custom watch implementation with [`watchexec`].
 
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use console::style;
 use miette::IntoDiagnostic;
+use tokio::sync::mpsc::{self, UnboundedSender};
 use watchexec::{
     action::{Action, Outcome},
     config::{InitConfig, RuntimeConfig},
     Watchexec,
 };
 
-use crate::{action::WAction, error::WatchError, framework::WatchableFramework};
+#[cfg(unix)]
+use crate::keyboard;
+use crate::{
+    action::WAction,
+    control,
+    error::WatchError,
+    framework::WatchableFramework,
+    hook::HookEvent,
+    logs,
+    output::{print_error, OutputFormat},
+    tui,
+};
 
 /// Watch the changes based on the specific [`WatchableFramework`] implementation.
 pub async fn watch(framework: Arc<dyn WatchableFramework>) -> miette::Result<()> {
-    framework.initialize().await?;
+    if OutputFormat::get() == OutputFormat::Tui {
+        // The dashboard needs to subscribe to the events before watching starts, `biased` makes
+        // sure it's polled first
+        return tokio::select! {
+            biased;
+            result = tui::run(framework.clone()) => result,
+            result = run(framework) => result,
+        };
+    }
+
+    run(framework).await
+}
+
+/// Initialize the framework and start watching.
+async fn run(framework: Arc<dyn WatchableFramework>) -> miette::Result<()> {
+    if let Err(err) = framework.initialize().await {
+        emit_error(framework.as_ref(), &err).await;
+        return Err(err);
+    }
+
+    if framework.config().logs.enabled {
+        logs::start(framework.clone()).await;
+    }
 
+    #[cfg(unix)]
+    if framework.config().control.enabled {
+        let socket_path = framework
+            .config()
+            .control
+            .get_socket_path(framework.origin());
+        tokio::spawn({
+            let framework = framework.clone();
+            async move {
+                if let Err(err) = control::serve(framework.clone(), socket_path).await {
+                    report_error(framework.as_ref(), err).await;
+                }
+            }
+        });
+    }
+
+    let (reconfigure_tx, mut reconfigure_rx) = mpsc::unbounded_channel();
     let mut runtime = RuntimeConfig::default();
 
     runtime
-        .pathset(framework.pathset().await?)
+        .pathset(get_pathset(framework.as_ref()).await?)
         .filterer(framework.filterer().await)
         .action_throttle(Duration::from_millis(200))
-        .on_action(move |action| {
+        .on_action({
             let framework = framework.clone();
-            async move { on_action(action, framework).await }
+            move |action| {
+                let framework = framework.clone();
+                let reconfigure_tx = reconfigure_tx.clone();
+                async move { on_action(action, framework, reconfigure_tx).await }
+            }
         });
 
     let init = InitConfig::default();
 
-    let watchexec = Watchexec::new(init, runtime)?;
+    let watchexec = Watchexec::new(init, runtime.clone())?;
+
+    #[cfg(unix)]
+    let key_input_guard = match OutputFormat::is_human() {
+        true => keyboard::enable_key_input(),
+        false => None,
+    };
+    #[cfg(unix)]
+    if key_input_guard.is_some() {
+        tokio::spawn(keyboard::listen(framework.clone(), watchexec.clone()));
+    }
+
+    // Programs can be added or removed while watching, the pathset and the filterer need to be
+    // updated when that happens
+    tokio::spawn({
+        let watchexec = watchexec.clone();
+        async move {
+            while reconfigure_rx.recv().await.is_some() {
+                match get_pathset(framework.as_ref()).await {
+                    Ok(pathset) => {
+                        runtime
+                            .pathset(pathset)
+                            .filterer(framework.filterer().await);
+                        if let Err(err) = watchexec.reconfigure(runtime.clone()) {
+                            report_error(framework.as_ref(), err.into()).await;
+                        }
+                    }
+                    Err(err) => report_error(framework.as_ref(), err).await,
+                }
+            }
+        }
+    });
+
     watchexec.main().await.into_diagnostic()??;
 
     Ok(())
 }
 
+/// Get the paths to watch, including the discovery paths that exist.
+async fn get_pathset(framework: &dyn WatchableFramework) -> miette::Result<Vec<PathBuf>> {
+    let mut pathset = framework.pathset().await?;
+    pathset.extend(
+        framework
+            .discovery_paths()
+            .into_iter()
+            .filter(|path| path.exists()),
+    );
+
+    Ok(pathset)
+}
+
 /// Top level action handler.
 async fn on_action(
     action: Action,
     framework: Arc<dyn WatchableFramework>,
+    reconfigure_tx: UnboundedSender<()>,
 ) -> Result<(), WatchError> {
     let action = WAction::new(action);
 
@@ -49,9 +149,58 @@ async fn on_action(
         return Ok(());
     }
 
+    if control::is_paused() {
+        return Ok(());
+    }
+
+    // Only the changes of the discovery paths can add, remove or rename programs, save the
+    // current programs to reconfigure only if they have changed after the action
+    let previous_program_hm = match action
+        .get_unique_paths()
+        .into_iter()
+        .any(|path| framework.is_discovery_path(path))
+    {
+        true => Some(framework.project_map().get_all().await),
+        false => None,
+    };
+
+    let paths = action
+        .get_unique_paths()
+        .into_iter()
+        .map(|path| path.to_path_buf())
+        .collect::<Vec<_>>();
+    if !paths.is_empty() {
+        if let Err(err) = framework.emit(HookEvent::ChangeDetected { paths }).await {
+            report_error(framework.as_ref(), err).await;
+        }
+    }
+
     if let Err(err) = framework.on_action(action).await {
-        eprintln!("{} {}", style("[ERR]").red().bold(), err);
+        report_error(framework.as_ref(), err).await;
+    }
+
+    if let Some(previous_program_hm) = previous_program_hm {
+        if framework.project_map().get_all().await != previous_program_hm {
+            let _ = reconfigure_tx.send(());
+        }
     }
 
     Ok(())
 }
+
+/// Print the error and emit [`HookEvent::Error`].
+async fn report_error(framework: &dyn WatchableFramework, err: miette::Report) {
+    print_error(&err);
+    emit_error(framework, &err).await;
+}
+
+/// Emit [`HookEvent::Error`].
+///
+/// Errors of the error hooks are ignored to not end up in a loop.
+async fn emit_error(framework: &dyn WatchableFramework, err: &miette::Report) {
+    let _ = framework
+        .emit(HookEvent::Error {
+            message: err.to_string(),
+        })
+        .await;
+}